@@ -0,0 +1,185 @@
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use my_glium_util::datastruct::{
+    aabb::Aabb,
+    points::As2dPoint,
+    quadtree::{ArenaQuadtree, Quadtree, QueryStack},
+};
+
+#[derive(Debug, Clone)]
+struct Particle {
+    x: f32,
+    y: f32,
+}
+
+impl As2dPoint<f32> for Particle {
+    fn x(&self) -> f32 {
+        self.x
+    }
+    fn y(&self) -> f32 {
+        self.y
+    }
+}
+
+const PARTICLE_NB: usize = 10_000;
+const QUERY_HALF_DIM: f32 = 15.;
+
+fn particles() -> Vec<Particle> {
+    (0..PARTICLE_NB)
+        .map(|i| {
+            let t = i as f32 * 0.61;
+            let r = (i as f32 / PARTICLE_NB as f32).sqrt() * 500.;
+            Particle {
+                x: t.cos() * r,
+                y: t.sin() * r,
+            }
+        })
+        .collect()
+}
+
+fn bench_query_range(c: &mut Criterion) {
+    let qtree: Quadtree<f32, Particle, 8> = Quadtree::new(Aabb::new((0., 0.), 500.), particles());
+    let ranges: Vec<_> = qtree
+        .iter()
+        .map(|p| Aabb::new((p.x, p.y), QUERY_HALF_DIM))
+        .collect();
+
+    let mut group = c.benchmark_group("quadtree query_range");
+
+    //allocates a vec per query, the baseline of the lazy versions
+    group.bench_function("query_range_collect", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            for range in &ranges {
+                count += qtree.query_range_iter(*range).collect::<Vec<_>>().len();
+            }
+            black_box(count)
+        })
+    });
+
+    group.bench_function("query_range_iter", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            for range in &ranges {
+                count += qtree.query_range_iter(*range).count();
+            }
+            black_box(count)
+        })
+    });
+
+    group.bench_function("query_range_iter_with", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            let mut stack = QueryStack::new();
+            for range in &ranges {
+                let mut iter = qtree.query_range_iter_with(*range, stack);
+                count += iter.by_ref().count();
+                stack = iter.into_stack();
+            }
+            black_box(count)
+        })
+    });
+
+    group.bench_function("query_range_into", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            let mut result = vec![];
+            for range in &ranges {
+                result.clear();
+                qtree.query_range_into(*range, &mut result);
+                count += result.len();
+            }
+            black_box(count)
+        })
+    });
+
+    group.finish();
+}
+
+fn bench_backends(c: &mut Criterion) {
+    let boundary = Aabb::new((0., 0.), 500.);
+    let particles = particles();
+    let ranges: Vec<_> = particles
+        .iter()
+        .map(|p| Aabb::new((p.x, p.y), QUERY_HALF_DIM))
+        .collect();
+
+    let mut group = c.benchmark_group("quadtree backends");
+
+    group.bench_function("build boxed", |b| {
+        b.iter(|| {
+            let qtree: Quadtree<f32, Particle, 8> = Quadtree::new(boundary, particles.clone());
+            black_box(qtree.len())
+        })
+    });
+
+    group.bench_function("build arena", |b| {
+        b.iter(|| {
+            let qtree: ArenaQuadtree<f32, Particle, 8> =
+                ArenaQuadtree::new(boundary, particles.clone());
+            black_box(qtree.len())
+        })
+    });
+
+    group.bench_function("insert boxed", |b| {
+        b.iter(|| {
+            let mut qtree: Quadtree<f32, Particle, 8> = Quadtree::empty(boundary);
+            for p in &particles {
+                qtree.insert(p.clone()).unwrap();
+            }
+            black_box(qtree.len())
+        })
+    });
+
+    group.bench_function("insert arena", |b| {
+        b.iter(|| {
+            let mut qtree: ArenaQuadtree<f32, Particle, 8> = ArenaQuadtree::empty(boundary);
+            for p in &particles {
+                qtree.insert(p.clone()).unwrap();
+            }
+            black_box(qtree.len())
+        })
+    });
+
+    let boxed: Quadtree<f32, Particle, 8> = Quadtree::new(boundary, particles.clone());
+    group.bench_function("query boxed", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            for range in &ranges {
+                count += boxed.query_range(*range).len();
+            }
+            black_box(count)
+        })
+    });
+
+    let mut arena: ArenaQuadtree<f32, Particle, 8> = ArenaQuadtree::empty(boundary);
+    for p in &particles {
+        arena.insert(p.clone()).unwrap();
+    }
+    group.bench_function("query arena (inserted)", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            for range in &ranges {
+                count += arena.query_range(*range).len();
+            }
+            black_box(count)
+        })
+    });
+
+    arena.rebuild().unwrap();
+    group.bench_function("query arena (morton ordered)", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            for range in &ranges {
+                count += arena.query_range(*range).len();
+            }
+            black_box(count)
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_query_range, bench_backends);
+criterion_main!(benches);
@@ -3,12 +3,26 @@ mod test;
 
 use num::Float;
 
-use super::points::{As2dPoint, Point};
+use super::points::{As2dPoint, As3dPoint, Point, Point3};
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Aabb<F: Float + Copy> {
     pub center: Point<F>,
-    pub half_dim: F,
+    pub half_width: F,
+    pub half_height: F,
+}
+
+///Objects with a size, that can be stored in a `LooseQuadtree`.
+pub trait Bounded2d<F: Float + Copy> {
+    fn aabb(&self) -> Aabb<F>;
+}
+
+impl<F: Float + Copy> Bounded2d<F> for Aabb<F> {
+    #[inline(always)]
+    fn aabb(&self) -> Aabb<F> {
+        *self
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -20,14 +34,52 @@ pub enum DiagonalDirection {
 }
 
 impl<F: Float + Copy> Aabb<F> {
+    ///Square box.
     pub fn new(center: (F, F), half_width: F) -> Self {
+        Self::new_rect(center, half_width, half_width)
+    }
+
+    pub fn new_rect(center: (F, F), half_width: F, half_height: F) -> Self {
         debug_assert!(half_width > F::zero(), "half width should always be > 0.");
+        debug_assert!(half_height > F::zero(), "half height should always be > 0.");
         Self {
             center: center.as_point(),
-            half_dim: half_width,
+            half_width,
+            half_height,
+        }
+    }
+
+    #[inline(always)]
+    pub fn min(self) -> Point<F> {
+        Point {
+            x: self.center.x - self.half_width,
+            y: self.center.y - self.half_height,
         }
     }
 
+    #[inline(always)]
+    pub fn max(self) -> Point<F> {
+        Point {
+            x: self.center.x + self.half_width,
+            y: self.center.y + self.half_height,
+        }
+    }
+
+    #[inline(always)]
+    pub fn width(self) -> F {
+        self.half_width + self.half_width
+    }
+
+    #[inline(always)]
+    pub fn height(self) -> F {
+        self.half_height + self.half_height
+    }
+
+    #[inline(always)]
+    pub fn area(self) -> F {
+        self.width() * self.height()
+    }
+
     pub fn tchebychev_dist(self, point: Point<F>) -> F {
         let dx = (point.x - self.center.x).abs();
         let dy = (point.y - self.center.y).abs();
@@ -36,11 +88,88 @@ impl<F: Float + Copy> Aabb<F> {
 
     #[inline(always)]
     pub fn contain_pt(self, point: Point<F>) -> bool {
-        self.tchebychev_dist(point) <= self.half_dim
+        (point.x - self.center.x).abs() <= self.half_width
+            && (point.y - self.center.y).abs() <= self.half_height
     }
 
+    ///true if `other` is entirely inside the box
+    #[inline(always)]
+    pub fn contain_aabb(self, other: Self) -> bool {
+        self.contain_pt(other.min()) && self.contain_pt(other.max())
+    }
+
+    ///squared euclidean distance from the point to the box, 0 if the point is inside
+    #[inline(always)]
+    pub fn dist_sq_to_pt(self, point: Point<F>) -> F {
+        let dx = ((point.x - self.center.x).abs() - self.half_width).max(F::zero());
+        let dy = ((point.y - self.center.y).abs() - self.half_height).max(F::zero());
+
+        dx * dx + dy * dy
+    }
+
+    ///true if the boxes share at least a point, boxes touching by an edge intersect
     pub fn intersect(self, other: Self) -> bool {
-        self.tchebychev_dist(other.center) < self.half_dim + other.half_dim
+        (other.center.x - self.center.x).abs() <= self.half_width + other.half_width
+            && (other.center.y - self.center.y).abs() <= self.half_height + other.half_height
+    }
+
+    ///Slab test: the part `[t_enter, t_exit]` of `[0, max_t]` where `origin + t * dir` is in the box,
+    ///`None` if the ray misses it.
+    pub fn ray_intersection(self, origin: Point<F>, dir: Point<F>, max_t: F) -> Option<(F, F)> {
+        let (min, max) = (self.min(), self.max());
+        let mut t_enter = F::zero();
+        let mut t_exit = max_t;
+
+        for (o, d, min, max) in [
+            (origin.x, dir.x, min.x, max.x),
+            (origin.y, dir.y, min.y, max.y),
+        ] {
+            if d == F::zero() {
+                if o < min || o > max {
+                    return None;
+                }
+                continue;
+            }
+            let (t_min, t_max) = ((min - o) / d, (max - o) / d);
+            t_enter = t_enter.max(t_min.min(t_max));
+            t_exit = t_exit.min(t_min.max(t_max));
+        }
+
+        (t_enter <= t_exit).then_some((t_enter, t_exit))
+    }
+
+    ///smallest box containing both boxes
+    pub fn union(self, other: Self) -> Self {
+        let (min, max) = (self.min(), self.max());
+        let (o_min, o_max) = (other.min(), other.max());
+
+        Self::from_min_max(
+            (min.x.min(o_min.x), min.y.min(o_min.y)),
+            (max.x.max(o_max.x), max.y.max(o_max.y)),
+        )
+    }
+
+    ///box shared by both boxes, `None` if they do not intersect
+    pub fn intersection(self, other: Self) -> Option<Self> {
+        if !self.intersect(other) {
+            return None;
+        }
+        let (min, max) = (self.min(), self.max());
+        let (o_min, o_max) = (other.min(), other.max());
+
+        Some(Self::from_min_max(
+            (min.x.max(o_min.x), min.y.max(o_min.y)),
+            (max.x.min(o_max.x), max.y.min(o_max.y)),
+        ))
+    }
+
+    ///box grown by `margin` on every side, shrunk if `margin` is negative
+    pub fn expand(self, margin: F) -> Self {
+        Self {
+            center: self.center,
+            half_width: (self.half_width + margin).max(F::zero()),
+            half_height: (self.half_height + margin).max(F::zero()),
+        }
     }
 
     #[inline(always)]
@@ -57,7 +186,8 @@ impl<F: Float + Copy> Aabb<F> {
         let two = F::one() + F::one();
         let min_one = -F::one();
         let one = F::one();
-        let quart_dim = self.half_dim / two;
+        let quart_width = self.half_width / two;
+        let quart_height = self.half_height / two;
         let offsets = [
             (min_one, one),
             (one, one),
@@ -67,11 +197,12 @@ impl<F: Float + Copy> Aabb<F> {
 
         offsets.map(|(dx, dy)| Self {
             center: (
-                self.center.x + dx * quart_dim,
-                self.center.y + dy * quart_dim,
+                self.center.x + dx * quart_width,
+                self.center.y + dy * quart_height,
             )
                 .as_point(),
-            half_dim: quart_dim,
+            half_width: quart_width,
+            half_height: quart_height,
         })
     }
 
@@ -81,9 +212,143 @@ impl<F: Float + Copy> Aabb<F> {
         let two = F::one() + F::one();
 
         let center = ((max.x + min.x) / (two), (max.y + min.y) / two).as_point();
-        let half_dim = min.tchebychev_dist(max) / two;
+        let half_width = (max.x - min.x).abs() / two;
+        let half_height = (max.y - min.y).abs() / two;
 
-        Self { center, half_dim }
+        Self {
+            center,
+            half_width,
+            half_height,
+        }
+    }
+}
+
+///3d box, see [`Aabb`].
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Aabb3<F: Float + Copy> {
+    pub center: Point3<F>,
+    pub half_width: F,
+    pub half_height: F,
+    pub half_depth: F,
+}
+
+impl<F: Float + Copy> Aabb3<F> {
+    ///Cube.
+    pub fn new(center: (F, F, F), half_width: F) -> Self {
+        Self::new_box(center, half_width, half_width, half_width)
+    }
+
+    pub fn new_box(center: (F, F, F), half_width: F, half_height: F, half_depth: F) -> Self {
+        debug_assert!(half_width > F::zero(), "half width should always be > 0.");
+        debug_assert!(half_height > F::zero(), "half height should always be > 0.");
+        debug_assert!(half_depth > F::zero(), "half depth should always be > 0.");
+        Self {
+            center: center.as_point3(),
+            half_width,
+            half_height,
+            half_depth,
+        }
+    }
+
+    pub fn from_min_max<T: As3dPoint<F>, U: As3dPoint<F>>(min: T, max: U) -> Self {
+        let min = min.as_point3();
+        let max = max.as_point3();
+        let two = F::one() + F::one();
+
+        Self {
+            center: Point3 {
+                x: (max.x + min.x) / two,
+                y: (max.y + min.y) / two,
+                z: (max.z + min.z) / two,
+            },
+            half_width: (max.x - min.x).abs() / two,
+            half_height: (max.y - min.y).abs() / two,
+            half_depth: (max.z - min.z).abs() / two,
+        }
+    }
+
+    #[inline(always)]
+    pub fn min(self) -> Point3<F> {
+        Point3 {
+            x: self.center.x - self.half_width,
+            y: self.center.y - self.half_height,
+            z: self.center.z - self.half_depth,
+        }
+    }
+
+    #[inline(always)]
+    pub fn max(self) -> Point3<F> {
+        Point3 {
+            x: self.center.x + self.half_width,
+            y: self.center.y + self.half_height,
+            z: self.center.z + self.half_depth,
+        }
+    }
+
+    #[inline(always)]
+    pub fn volume(self) -> F {
+        let eight = F::from(8).unwrap();
+        eight * self.half_width * self.half_height * self.half_depth
+    }
+
+    #[inline(always)]
+    pub fn contain_pt(self, point: Point3<F>) -> bool {
+        (point.x - self.center.x).abs() <= self.half_width
+            && (point.y - self.center.y).abs() <= self.half_height
+            && (point.z - self.center.z).abs() <= self.half_depth
+    }
+
+    ///true if `other` is entirely inside the box
+    #[inline(always)]
+    pub fn contain_aabb(self, other: Self) -> bool {
+        self.contain_pt(other.min()) && self.contain_pt(other.max())
+    }
+
+    ///squared euclidean distance from the point to the box, 0 if the point is inside
+    #[inline(always)]
+    pub fn dist_sq_to_pt(self, point: Point3<F>) -> F {
+        let dx = ((point.x - self.center.x).abs() - self.half_width).max(F::zero());
+        let dy = ((point.y - self.center.y).abs() - self.half_height).max(F::zero());
+        let dz = ((point.z - self.center.z).abs() - self.half_depth).max(F::zero());
+
+        dx * dx + dy * dy + dz * dz
+    }
+
+    ///true if the boxes share at least a point, boxes touching by a face intersect
+    pub fn intersect(self, other: Self) -> bool {
+        (other.center.x - self.center.x).abs() <= self.half_width + other.half_width
+            && (other.center.y - self.center.y).abs() <= self.half_height + other.half_height
+            && (other.center.z - self.center.z).abs() <= self.half_depth + other.half_depth
+    }
+
+    ///Index of the octant of `point` in `subdivide`: bit 0 set if x > center.x,
+    ///bit 1 if y > center.y, bit 2 if z > center.z.
+    #[inline(always)]
+    pub fn octant(&self, point: Point3<F>) -> usize {
+        (point.x > self.center.x) as usize
+            | ((point.y > self.center.y) as usize) << 1
+            | ((point.z > self.center.z) as usize) << 2
+    }
+
+    ///The 8 octants, ordered as in `octant`.
+    pub fn subdivide(self) -> [Self; 8] {
+        let two = F::one() + F::one();
+        let quart_width = self.half_width / two;
+        let quart_height = self.half_height / two;
+        let quart_depth = self.half_depth / two;
+        let sign = |bit: bool| if bit { F::one() } else { -F::one() };
+
+        std::array::from_fn(|octant| Self {
+            center: Point3 {
+                x: self.center.x + sign(octant & 1 != 0) * quart_width,
+                y: self.center.y + sign(octant & 2 != 0) * quart_height,
+                z: self.center.z + sign(octant & 4 != 0) * quart_depth,
+            },
+            half_width: quart_width,
+            half_height: quart_height,
+            half_depth: quart_depth,
+        })
     }
 }
 
@@ -94,8 +359,8 @@ impl<F: Float + Copy> Aabb<F> {
 //         if is_x86_feature_detected!("sse4.1"){
 //             unsafe {
 //                 let pt = _mm_set_ps(0.0, 0.0, y, x);
-//                 let min = _mm_set_ps(0.0, 0.0, self.center.y - self.half_dim, self.center.x - self.half_dim);
-//                 let max = _mm_set_ps(0.0, 0.0, self.center.y + self.half_dim, self.center.x + self.half_dim);
+//                 let min = _mm_set_ps(0.0, 0.0, self.center.y - self.half_height, self.center.x - self.half_width);
+//                 let max = _mm_set_ps(0.0, 0.0, self.center.y + self.half_height, self.center.x + self.half_width);
 
 //                 let gt_min = _mm_cmpge_ps(pt, min);
 //                 let lt_max = _mm_cmpge_ps(pt, max);
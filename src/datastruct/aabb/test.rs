@@ -1,8 +1,8 @@
 #![cfg(test)]
 
 use crate::datastruct::{
-    aabb::Aabb,
-    points::{As2dPoint, Point},
+    aabb::{Aabb, Aabb3},
+    points::{As2dPoint, As3dPoint, Point},
 };
 
 #[test]
@@ -19,10 +19,17 @@ fn test_aabb_contain_point1() {
 fn test_aabb_intersect() {
     let aabb1 = Aabb::new((5.0, 5.0), 3.0);
     let aabb2 = Aabb::new((6.0, 6.0), 3.0);
-    let aabb3 = Aabb::new((10.0, 10.0), 2.0);
+    let aabb3 = Aabb::new((10.0, 10.0), 1.5);
+    let aabb4 = Aabb::new((10.0, 10.0), 2.0);
 
     assert!(aabb1.intersect(aabb2));
     assert!(!aabb1.intersect(aabb3));
+    //touching boxes
+    assert!(aabb1.intersect(aabb4));
+
+    let wide = Aabb::new_rect((0.0, 0.0), 10.0, 1.0);
+    assert!(wide.intersect(Aabb::new((9.0, 0.0), 0.5)));
+    assert!(!wide.intersect(Aabb::new((0.0, 3.0), 1.5)));
 }
 
 #[test]
@@ -31,7 +38,14 @@ fn test_aabb_subdivide() {
     let quadrants = aabb.subdivide();
 
     assert_eq!(quadrants.len(), 4);
-    assert_eq!(quadrants[0].half_dim, 2.0);
+    assert_eq!(quadrants[0].half_width, 2.0);
+    assert_eq!(quadrants[0].half_height, 2.0);
+
+    let rect = Aabb::new_rect((0.0, 0.0), 8.0, 2.0);
+    for quadrant in rect.subdivide() {
+        assert_eq!((quadrant.half_width, quadrant.half_height), (4.0, 1.0));
+        assert!(rect.contain_aabb(quadrant));
+    }
 }
 
 #[test]
@@ -45,4 +59,121 @@ fn test_aabb_from_min_max() {
     assert!(aabb.contain_pt(min.as_point()));
     assert!(aabb.contain_pt(max.as_point()));
     assert!(aabb.contain_pt(mid.as_point()));
+
+    let screen = Aabb::from_min_max((0., 0.), (1920., 1080.));
+    assert_eq!((screen.half_width, screen.half_height), (960., 540.));
+    assert!(screen.contain_pt((1920., 1080.).as_point()));
+    assert!(!screen.contain_pt((1080., 1920.).as_point()));
+}
+
+#[test]
+fn test_aabb_union_intersection() {
+    let a = Aabb::from_min_max((0., 0.), (4., 2.));
+    let b = Aabb::from_min_max((3., 1.), (6., 5.));
+
+    let union = a.union(b);
+    assert_eq!((union.min().x, union.min().y), (0., 0.));
+    assert_eq!((union.max().x, union.max().y), (6., 5.));
+
+    let inter = a.intersection(b).unwrap();
+    assert_eq!((inter.min().x, inter.min().y), (3., 1.));
+    assert_eq!((inter.max().x, inter.max().y), (4., 2.));
+
+    assert!(a.intersection(Aabb::new((10., 10.), 1.)).is_none());
+}
+
+#[test]
+fn test_aabb_area_expand() {
+    let a = Aabb::from_min_max((0., 0.), (4., 2.));
+    assert_eq!(a.area(), 8.);
+
+    let expanded = a.expand(1.);
+    assert_eq!((expanded.width(), expanded.height()), (6., 4.));
+    assert_eq!(expanded.area(), 24.);
+
+    assert_eq!(a.expand(-5.).area(), 0.);
+}
+
+#[test]
+fn test_aabb_ray_intersection() {
+    let aabb = Aabb::from_min_max((1., 1.), (3., 2.));
+    let origin = (0., 0.).as_point();
+
+    let (t_enter, t_exit) = aabb
+        .ray_intersection(origin, (1., 1.).as_point(), 10.)
+        .unwrap();
+    assert_eq!((t_enter, t_exit), (1., 2.));
+
+    //too short
+    assert!(
+        aabb.ray_intersection(origin, (1., 1.).as_point(), 0.5)
+            .is_none()
+    );
+    //wrong direction
+    assert!(
+        aabb.ray_intersection(origin, (-1., -1.).as_point(), 10.)
+            .is_none()
+    );
+    //parallel to an axis
+    let (t_enter, t_exit) = aabb
+        .ray_intersection((0., 1.5).as_point(), (1., 0.).as_point(), 10.)
+        .unwrap();
+    assert_eq!((t_enter, t_exit), (1., 3.));
+    assert!(
+        aabb.ray_intersection((0., 2.5).as_point(), (1., 0.).as_point(), 10.)
+            .is_none()
+    );
+    //starting inside
+    let (t_enter, _) = aabb
+        .ray_intersection((2., 1.5).as_point(), (0., 1.).as_point(), 10.)
+        .unwrap();
+    assert_eq!(t_enter, 0.);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_aabb_serde() {
+    let aabb = Aabb::new_rect((1.5, -2.), 3., 0.25);
+    let json = serde_json::to_string(&aabb).unwrap();
+    let back: Aabb<f64> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        (
+            back.center.x,
+            back.center.y,
+            back.half_width,
+            back.half_height
+        ),
+        (1.5, -2., 3., 0.25)
+    );
+}
+
+#[test]
+fn test_aabb3_subdivide_octant() {
+    let aabb = Aabb3::new_box((0., 0., 0.), 4., 2., 1.);
+    let octants = aabb.subdivide();
+
+    for (k, octant) in octants.into_iter().enumerate() {
+        assert_eq!(
+            (octant.half_width, octant.half_height, octant.half_depth),
+            (2., 1., 0.5)
+        );
+        assert!(aabb.contain_aabb(octant));
+        assert_eq!(aabb.octant(octant.center), k);
+    }
+    assert_eq!(aabb.octant((1., -1., 0.5).as_point3()), 0b101);
+    assert_eq!(aabb.volume(), 64.);
+    assert_eq!(octants.iter().map(|o| o.volume()).sum::<f64>(), 64.);
+}
+
+#[test]
+fn test_aabb3_contain_intersect_dist() {
+    let aabb = Aabb3::from_min_max((0., 0., 0.), (2., 4., 6.));
+    assert!(aabb.contain_pt((2., 0., 3.).as_point3()));
+    assert!(!aabb.contain_pt((1., 1., 6.5).as_point3()));
+
+    assert!(aabb.intersect(Aabb3::from_min_max((2., 4., 6.), (3., 5., 7.))));
+    assert!(!aabb.intersect(Aabb3::from_min_max((0., 0., 7.), (1., 1., 8.))));
+
+    assert_eq!(aabb.dist_sq_to_pt((1., 1., 1.).as_point3()), 0.);
+    assert_eq!(aabb.dist_sq_to_pt((3., 6., 6.).as_point3()), 5.);
 }
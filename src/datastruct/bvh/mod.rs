@@ -0,0 +1,623 @@
+#[cfg(test)]
+mod test;
+
+use super::{
+    aabb::Aabb3,
+    points::{As3dPoint, Point3},
+    util::partition,
+};
+use crate::mesh::vertex::Vertex;
+
+///above this number of triangles, a leaf is always split
+const MAX_LEAF_SIZE: usize = 4;
+///number of buckets the centroids are sorted in to evaluate the splits
+const SAH_BINS: usize = 12;
+///cost of visiting a node, relative to a ray-triangle test
+const TRAVERSAL_COST: f32 = 1.;
+///the nodes at this depth are leaves, whatever their number of triangles
+const MAX_DEPTH: usize = 64;
+
+///Integer types usable as vertex indices.
+pub trait VertexIndex: Copy {
+    fn index(self) -> usize;
+}
+
+impl VertexIndex for u16 {
+    #[inline(always)]
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl VertexIndex for u32 {
+    #[inline(always)]
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+///Bounding volume hierarchy over the triangles of a mesh, built with the surface area heuristic.
+///
+///Triangle `t` is made of the vertices `indices[3 * t..3 * t + 3]` of the index slice the
+///hierarchy was built from. The `w` coordinate of the vertices is ignored.
+///The hierarchy keeps a copy of the positions, call `refit` when the vertices move.
+#[derive(Debug, Clone)]
+pub struct Bvh {
+    ///the root is the first node, children always come after their parent
+    nodes: Vec<BvhNode>,
+    ///triangle ids, ordered so that each leaf refers to a contiguous run
+    order: Vec<usize>,
+    ///vertex indices of each triangle
+    triangles: Vec<[usize; 3]>,
+    positions: Vec<[f32; 3]>,
+}
+
+#[derive(Debug, Clone)]
+struct BvhNode {
+    min: [f32; 3],
+    max: [f32; 3],
+    kind: BvhNodeKind,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BvhNodeKind {
+    Inner {
+        left: usize,
+        right: usize,
+    },
+    ///triangles `order[first..first + count]`
+    Leaf {
+        first: usize,
+        count: usize,
+    },
+}
+
+///Intersection of a ray and a triangle.
+///The hit point is `origin + t * dir`, or `(1 - u - v) * a + u * b + v * c` with `[a, b, c]`
+///the vertices of the triangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub triangle: usize,
+    pub t: f32,
+    pub u: f32,
+    pub v: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BvhError {
+    ///the length of the index slice is not a multiple of 3
+    IncompleteTriangle(usize),
+    IndexOutOfBounds {
+        index: usize,
+        vertex_count: usize,
+    },
+    InvalidCoord([f32; 3]),
+}
+
+impl std::fmt::Display for BvhError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BvhError::IncompleteTriangle(len) => {
+                write!(f, "{} indices do not make whole triangles.", len)
+            }
+            BvhError::IndexOutOfBounds {
+                index,
+                vertex_count,
+            } => write!(
+                f,
+                "the index {} is out of the {} vertices.",
+                index, vertex_count
+            ),
+            BvhError::InvalidCoord(coord) => {
+                write!(f, "vertex of coord {:?} are invalid.", coord)
+            }
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl Bvh {
+    ///Builds the hierarchy over the triangles of `indices`, in O(n log n).
+    pub fn new<I: VertexIndex>(vertices: &[Vertex], indices: &[I]) -> Result<Self, BvhError> {
+        if !indices.len().is_multiple_of(3) {
+            return Err(BvhError::IncompleteTriangle(indices.len()));
+        }
+        let triangles: Vec<[usize; 3]> = indices
+            .chunks_exact(3)
+            .map(|tri| [tri[0].index(), tri[1].index(), tri[2].index()])
+            .collect();
+        if let Some(&index) = triangles.iter().flatten().find(|&&i| i >= vertices.len()) {
+            return Err(BvhError::IndexOutOfBounds {
+                index,
+                vertex_count: vertices.len(),
+            });
+        }
+
+        let mut result = Self {
+            nodes: vec![],
+            order: (0..triangles.len()).collect(),
+            triangles,
+            positions: vec![],
+        };
+        result.copy_positions(vertices)?;
+
+        if !result.triangles.is_empty() {
+            let centroids: Vec<[f32; 3]> = (0..result.triangles.len())
+                .map(|t| {
+                    let [a, b, c] = result.vertices_of(t);
+                    std::array::from_fn(|k| (a[k] + b[k] + c[k]) / 3.)
+                })
+                .collect();
+            result.build(0, result.order.len(), 1, &centroids);
+        }
+        Ok(result)
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn depth(&self) -> usize {
+        if self.nodes.is_empty() {
+            return 0;
+        }
+        let mut depth = 0;
+        let mut stack = vec![(0, 1)];
+        while let Some((curr, curr_depth)) = stack.pop() {
+            depth = depth.max(curr_depth);
+            if let BvhNodeKind::Inner { left, right } = self.nodes[curr].kind {
+                stack.push((left, curr_depth + 1));
+                stack.push((right, curr_depth + 1));
+            }
+        }
+        depth
+    }
+
+    ///Box containing the whole mesh, `None` if there is no triangle.
+    pub fn aabb(&self) -> Option<Aabb3<f32>> {
+        self.nodes
+            .first()
+            .map(|root| Aabb3::from_min_max(root.min, root.max))
+    }
+
+    ///The 3 vertices of triangle `t`.
+    pub fn triangle(&self, t: usize) -> Option<[Point3<f32>; 3]> {
+        self.triangles
+            .get(t)
+            .map(|tri| tri.map(|i| self.positions[i].as_point3()))
+    }
+
+    ///Closest intersection of the ray with the mesh, at most `max_t` away (in multiples of `dir`).
+    ///Triangles are hit from both sides.
+    pub fn raycast_first<P: As3dPoint<f32>, D: As3dPoint<f32>>(
+        &self,
+        origin: P,
+        dir: D,
+        max_t: f32,
+    ) -> Option<RayHit> {
+        let ray = Ray::new(origin, dir)?;
+        let mut best: Option<RayHit> = None;
+        let mut stack = vec![];
+        if let Some(t) = self.enter_root(&ray, max_t) {
+            stack.push((0, t));
+        }
+
+        while let Some((curr, t_enter)) = stack.pop() {
+            //nothing behind the closest hit found so far
+            let limit = best.map_or(max_t, |hit| hit.t);
+            if t_enter > limit {
+                continue;
+            }
+            match self.nodes[curr].kind {
+                BvhNodeKind::Leaf { first, count } => {
+                    for &t in &self.order[first..first + count] {
+                        if let Some(hit) = self.hit_triangle(&ray, t, limit)
+                            && best.is_none_or(|best| hit.t < best.t)
+                        {
+                            best = Some(hit);
+                        }
+                    }
+                }
+                BvhNodeKind::Inner { left, right } => {
+                    let enter_left = ray.enter(&self.nodes[left], limit);
+                    let enter_right = ray.enter(&self.nodes[right], limit);
+                    //the closest child is visited first
+                    let mut children = [(left, enter_left), (right, enter_right)];
+                    if enter_left > enter_right {
+                        children.swap(0, 1);
+                    }
+                    for (child, enter) in children.into_iter().rev() {
+                        if let Some(t) = enter {
+                            stack.push((child, t));
+                        }
+                    }
+                }
+            }
+        }
+        best
+    }
+
+    ///Every intersection of the ray with the mesh at most `max_t` away, sorted by distance.
+    pub fn raycast<P: As3dPoint<f32>, D: As3dPoint<f32>>(
+        &self,
+        origin: P,
+        dir: D,
+        max_t: f32,
+    ) -> Vec<RayHit> {
+        let Some(ray) = Ray::new(origin, dir) else {
+            return vec![];
+        };
+        let mut result = vec![];
+        let mut stack: Vec<usize> = vec![];
+        if self.enter_root(&ray, max_t).is_some() {
+            stack.push(0);
+        }
+
+        while let Some(curr) = stack.pop() {
+            match self.nodes[curr].kind {
+                BvhNodeKind::Leaf { first, count } => result.extend(
+                    self.order[first..first + count]
+                        .iter()
+                        .filter_map(|&t| self.hit_triangle(&ray, t, max_t)),
+                ),
+                BvhNodeKind::Inner { left, right } => stack.extend(
+                    [left, right]
+                        .into_iter()
+                        .filter(|&child| ray.enter(&self.nodes[child], max_t).is_some()),
+                ),
+            }
+        }
+        result.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap_or(std::cmp::Ordering::Equal));
+        result
+    }
+
+    ///Triangles overlapping `range` (touching counts), found with the separating axis test.
+    pub fn query_aabb(&self, range: Aabb3<f32>) -> Vec<usize> {
+        let (r_min, r_max) = (range.min(), range.max());
+        let (r_min, r_max) = ([r_min.x, r_min.y, r_min.z], [r_max.x, r_max.y, r_max.z]);
+        let center = [range.center.x, range.center.y, range.center.z];
+        let half = [range.half_width, range.half_height, range.half_depth];
+
+        let mut result = vec![];
+        let mut stack: Vec<usize> = if self.nodes.is_empty() {
+            vec![]
+        } else {
+            vec![0]
+        };
+        while let Some(curr) = stack.pop() {
+            let node = &self.nodes[curr];
+            if (0..3).any(|k| node.min[k] > r_max[k] || node.max[k] < r_min[k]) {
+                continue;
+            }
+            match node.kind {
+                BvhNodeKind::Leaf { first, count } => result.extend(
+                    self.order[first..first + count]
+                        .iter()
+                        .copied()
+                        .filter(|&t| triangle_overlaps_box(self.vertices_of(t), center, half)),
+                ),
+                BvhNodeKind::Inner { left, right } => stack.extend([left, right]),
+            }
+        }
+        result
+    }
+
+    ///Updates the boxes after the vertices moved, keeping the structure of the hierarchy.
+    ///`vertices` should have at least as many vertices as the one the hierarchy was built from.
+    ///On error, the hierarchy is left unchanged.
+    ///The queries stay exact but get slower as the mesh drifts from its shape at build time,
+    ///build a new hierarchy when it changed too much.
+    pub fn refit(&mut self, vertices: &[Vertex]) -> Result<(), BvhError> {
+        if vertices.len() < self.positions.len() {
+            return Err(BvhError::IndexOutOfBounds {
+                index: self.positions.len() - 1,
+                vertex_count: vertices.len(),
+            });
+        }
+        self.copy_positions(&vertices[..self.positions.len()])?;
+
+        //children come after their parent
+        for curr in (0..self.nodes.len()).rev() {
+            let (min, max) = match self.nodes[curr].kind {
+                BvhNodeKind::Leaf { first, count } => self.bounds(first, count),
+                BvhNodeKind::Inner { left, right } => {
+                    let (left, right) = (&self.nodes[left], &self.nodes[right]);
+                    (
+                        std::array::from_fn(|k| left.min[k].min(right.min[k])),
+                        std::array::from_fn(|k| left.max[k].max(right.max[k])),
+                    )
+                }
+            };
+            self.nodes[curr].min = min;
+            self.nodes[curr].max = max;
+        }
+        Ok(())
+    }
+
+    ///only the vertices of the triangles are checked, on error the positions are left unchanged
+    fn copy_positions(&mut self, vertices: &[Vertex]) -> Result<(), BvhError> {
+        if let Some(vertex) = self
+            .triangles
+            .iter()
+            .flatten()
+            .map(|&i| &vertices[i])
+            .find(|vertex| !vertex.position[..3].iter().all(|x| x.is_finite()))
+        {
+            let [x, y, z, _] = vertex.position;
+            return Err(BvhError::InvalidCoord([x, y, z]));
+        }
+        self.positions.clear();
+        self.positions.extend(vertices.iter().map(|vertex| {
+            let [x, y, z, _] = vertex.position;
+            [x, y, z]
+        }));
+        Ok(())
+    }
+
+    ///pushes the subtree of the triangles `order[first..first + count]`, returns its index.
+    ///`depth` counts this node.
+    fn build(&mut self, first: usize, count: usize, depth: usize, centroids: &[[f32; 3]]) -> usize {
+        let index = self.nodes.len();
+        let (min, max) = self.bounds(first, count);
+        self.nodes.push(BvhNode {
+            min,
+            max,
+            kind: BvhNodeKind::Leaf { first, count },
+        });
+        if count == 1 || depth >= MAX_DEPTH {
+            return index;
+        }
+
+        let mid = match self.best_split(first, count, centroids, surface_area(min, max)) {
+            Some((cost, axis, bin, c_min, c_max))
+                if cost < count as f32 || count > MAX_LEAF_SIZE =>
+            {
+                partition(&mut self.order[first..first + count], |&t| {
+                    bin_of(centroids[t][axis], c_min, c_max) < bin
+                })
+            }
+            //every centroid is at the same place, any split is as good
+            None if count > MAX_LEAF_SIZE => count / 2,
+            _ => return index,
+        };
+
+        let left = self.build(first, mid, depth + 1, centroids);
+        let right = self.build(first + mid, count - mid, depth + 1, centroids);
+        self.nodes[index].kind = BvhNodeKind::Inner { left, right };
+        index
+    }
+
+    ///cheapest split of the triangles `order[first..first + count]` by SAH binning on the
+    ///centroids: (cost, axis, first bin of the right side, centroid bounds on the axis)
+    fn best_split(
+        &self,
+        first: usize,
+        count: usize,
+        centroids: &[[f32; 3]],
+        parent_area: f32,
+    ) -> Option<(f32, usize, usize, f32, f32)> {
+        let run = &self.order[first..first + count];
+        let empty = ([f32::INFINITY; 3], [f32::NEG_INFINITY; 3]);
+        let union = |(a_min, a_max): ([f32; 3], [f32; 3]), (b_min, b_max): ([f32; 3], [f32; 3])| {
+            (
+                std::array::from_fn(|k| a_min[k].min(b_min[k])),
+                std::array::from_fn(|k| a_max[k].max(b_max[k])),
+            )
+        };
+        let mut best: Option<(f32, usize, usize, f32, f32)> = None;
+
+        for axis in [0, 1, 2] {
+            let (c_min, c_max) = run
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &t| {
+                    (lo.min(centroids[t][axis]), hi.max(centroids[t][axis]))
+                });
+            if c_max <= c_min {
+                continue;
+            }
+
+            let mut counts = [0usize; SAH_BINS];
+            let mut boxes = [empty; SAH_BINS];
+            for &t in run {
+                let bin = bin_of(centroids[t][axis], c_min, c_max);
+                counts[bin] += 1;
+                let [a, b, c] = self.vertices_of(t);
+                let tri_box = (
+                    std::array::from_fn(|k| a[k].min(b[k]).min(c[k])),
+                    std::array::from_fn(|k| a[k].max(b[k]).max(c[k])),
+                );
+                boxes[bin] = union(boxes[bin], tri_box);
+            }
+
+            //`left[split]` holds the bins before `split`, `right[split]` the others
+            let mut left = [(0, empty); SAH_BINS];
+            let mut right = [(0, empty); SAH_BINS];
+            for split in 1..SAH_BINS {
+                let (nb, aabb) = left[split - 1];
+                left[split] = (nb + counts[split - 1], union(aabb, boxes[split - 1]));
+            }
+            right[SAH_BINS - 1] = (counts[SAH_BINS - 1], boxes[SAH_BINS - 1]);
+            for split in (1..SAH_BINS - 1).rev() {
+                let (nb, aabb) = right[split + 1];
+                right[split] = (nb + counts[split], union(aabb, boxes[split]));
+            }
+
+            for split in 1..SAH_BINS {
+                let ((left_nb, (l_min, l_max)), (right_nb, (r_min, r_max))) =
+                    (left[split], right[split]);
+                if left_nb == 0 || right_nb == 0 {
+                    continue;
+                }
+                let cost = TRAVERSAL_COST
+                    + (surface_area(l_min, l_max) * left_nb as f32
+                        + surface_area(r_min, r_max) * right_nb as f32)
+                        / parent_area.max(f32::MIN_POSITIVE);
+                if best.is_none_or(|(best_cost, ..)| cost < best_cost) {
+                    best = Some((cost, axis, split, c_min, c_max));
+                }
+            }
+        }
+        best
+    }
+
+    ///box of the triangles `order[first..first + count]`
+    fn bounds(&self, first: usize, count: usize) -> ([f32; 3], [f32; 3]) {
+        let mut min = [f32::INFINITY; 3];
+        let mut max = [f32::NEG_INFINITY; 3];
+        for &t in &self.order[first..first + count] {
+            for p in self.vertices_of(t) {
+                for k in 0..3 {
+                    min[k] = min[k].min(p[k]);
+                    max[k] = max[k].max(p[k]);
+                }
+            }
+        }
+        (min, max)
+    }
+
+    #[inline(always)]
+    fn vertices_of(&self, t: usize) -> [[f32; 3]; 3] {
+        self.triangles[t].map(|i| self.positions[i])
+    }
+
+    fn enter_root(&self, ray: &Ray, max_t: f32) -> Option<f32> {
+        self.nodes.first().and_then(|root| ray.enter(root, max_t))
+    }
+
+    ///Möller–Trumbore
+    fn hit_triangle(&self, ray: &Ray, t: usize, max_t: f32) -> Option<RayHit> {
+        let [a, b, c] = self.vertices_of(t);
+        let edge1 = sub(b, a);
+        let edge2 = sub(c, a);
+        let p = cross(ray.dir, edge2);
+        let det = dot(edge1, p);
+        //`det` scales with the edges and with the direction
+        let scale = dot(edge1, edge1).max(dot(edge2, edge2)) * ray.dir_len;
+        if det.abs() < f32::EPSILON * scale {
+            //the ray is parallel to the triangle, or the triangle is degenerate
+            return None;
+        }
+
+        let inv_det = det.recip();
+        let s = sub(ray.origin, a);
+        let u = dot(s, p) * inv_det;
+        if !(0. ..=1.).contains(&u) {
+            return None;
+        }
+        let q = cross(s, edge1);
+        let v = dot(ray.dir, q) * inv_det;
+        if v < 0. || u + v > 1. {
+            return None;
+        }
+        let hit_t = dot(edge2, q) * inv_det;
+        (0. ..=max_t).contains(&hit_t).then_some(RayHit {
+            triangle: t,
+            t: hit_t,
+            u,
+            v,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Ray {
+    origin: [f32; 3],
+    dir: [f32; 3],
+    ///length of `dir`, which is not normalised since `t` is in multiples of it
+    dir_len: f32,
+}
+
+impl Ray {
+    ///`None` for a null or invalid direction
+    fn new<P: As3dPoint<f32>, D: As3dPoint<f32>>(origin: P, dir: D) -> Option<Self> {
+        let origin = [origin.x(), origin.y(), origin.z()];
+        let dir = [dir.x(), dir.y(), dir.z()];
+        let valid = origin.iter().chain(&dir).all(|x| x.is_finite());
+        (valid && dir != [0.; 3]).then(|| Self {
+            origin,
+            dir,
+            dir_len: dot(dir, dir).sqrt(),
+        })
+    }
+
+    ///slab test, the `t` where the ray enters the box, `None` if it misses it before `max_t`
+    fn enter(&self, node: &BvhNode, max_t: f32) -> Option<f32> {
+        let mut t_enter = 0f32;
+        let mut t_exit = max_t;
+
+        for k in 0..3 {
+            let (o, d) = (self.origin[k], self.dir[k]);
+            if d == 0. {
+                if o < node.min[k] || o > node.max[k] {
+                    return None;
+                }
+                continue;
+            }
+            let (t_min, t_max) = ((node.min[k] - o) / d, (node.max[k] - o) / d);
+            t_enter = t_enter.max(t_min.min(t_max));
+            t_exit = t_exit.min(t_min.max(t_max));
+        }
+
+        (t_enter <= t_exit).then_some(t_enter)
+    }
+}
+
+#[inline(always)]
+fn bin_of(centroid: f32, c_min: f32, c_max: f32) -> usize {
+    (((centroid - c_min) / (c_max - c_min) * SAH_BINS as f32) as usize).min(SAH_BINS - 1)
+}
+
+fn surface_area(min: [f32; 3], max: [f32; 3]) -> f32 {
+    let [dx, dy, dz] = std::array::from_fn(|k| (max[k] - min[k]).max(0.));
+    2. * (dx * dy + dy * dz + dz * dx)
+}
+
+///separating axis test between a triangle and the box `center ± half`
+fn triangle_overlaps_box(triangle: [[f32; 3]; 3], center: [f32; 3], half: [f32; 3]) -> bool {
+    let v = triangle.map(|p| sub(p, center));
+    let edges = [sub(v[1], v[0]), sub(v[2], v[1]), sub(v[0], v[2])];
+    let separated = |axis: [f32; 3]| {
+        let proj = v.map(|p| dot(p, axis));
+        let radius = (0..3).map(|k| half[k] * axis[k].abs()).sum::<f32>();
+        proj.iter().copied().fold(f32::INFINITY, f32::min) > radius
+            || proj.iter().copied().fold(f32::NEG_INFINITY, f32::max) < -radius
+    };
+
+    //the faces of the box
+    let unit_axes: [[f32; 3]; 3] =
+        std::array::from_fn(|k| std::array::from_fn(|j| (j == k) as u8 as f32));
+    if unit_axes.iter().any(|&axis| separated(axis)) {
+        return false;
+    }
+    //the plane of the triangle
+    if separated(cross(edges[0], edges[1])) {
+        return false;
+    }
+    //the edges of the box crossed with the edges of the triangle
+    !unit_axes
+        .iter()
+        .any(|&axis| edges.iter().any(|&edge| separated(cross(axis, edge))))
+}
+
+#[inline(always)]
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+#[inline(always)]
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[inline(always)]
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
@@ -0,0 +1,276 @@
+#![cfg(test)]
+
+use crate::{
+    datastruct::{
+        aabb::Aabb3,
+        bvh::{Bvh, BvhError, RayHit},
+        points::As3dPoint,
+        test_util::Rng,
+    },
+    mesh::vertex::Vertex,
+};
+
+///`n * n` cells of 2 triangles over `[0, n]²`, with a bumpy height
+fn terrain(n: u16) -> (Vec<Vertex>, Vec<u16>) {
+    let height = |x: f32, y: f32| (x * 0.7).sin() * (y * 0.5).cos() * 2.;
+    let vertices = (0..=n)
+        .flat_map(|y| (0..=n).map(move |x| (x as f32, y as f32)))
+        .map(|(x, y)| Vertex::from([x, y, height(x, y), 1.]))
+        .collect();
+    let at = |x: u16, y: u16| y * (n + 1) + x;
+    let indices = (0..n)
+        .flat_map(|y| (0..n).map(move |x| (x, y)))
+        .flat_map(|(x, y)| {
+            [
+                at(x, y),
+                at(x + 1, y),
+                at(x + 1, y + 1),
+                at(x, y),
+                at(x + 1, y + 1),
+                at(x, y + 1),
+            ]
+        })
+        .collect();
+    (vertices, indices)
+}
+
+///every hit, testing all the triangles
+fn brute_force_raycast(bvh: &Bvh, origin: [f32; 3], dir: [f32; 3], max_t: f32) -> Vec<RayHit> {
+    let mut hits: Vec<_> = (0..bvh.triangle_count())
+        .filter_map(|t| {
+            let single = single_triangle(bvh, t);
+            single
+                .raycast_first(origin, dir, max_t)
+                .map(|hit| RayHit { triangle: t, ..hit })
+        })
+        .collect();
+    hits.sort_by(|a, b| a.t.partial_cmp(&b.t).unwrap());
+    hits
+}
+
+fn single_triangle(bvh: &Bvh, t: usize) -> Bvh {
+    let vertices: Vec<Vertex> = bvh
+        .triangle(t)
+        .unwrap()
+        .iter()
+        .map(|p| Vertex::from([p.x, p.y, p.z, 1.]))
+        .collect();
+    Bvh::new(&vertices, &[0u16, 1, 2]).unwrap()
+}
+
+#[test]
+fn test_bvh_build() {
+    let (vertices, indices) = terrain(32);
+    let bvh = Bvh::new(&vertices, &indices).unwrap();
+    assert_eq!(bvh.triangle_count(), 2048);
+    assert!(bvh.depth() > 5 && bvh.depth() < 30);
+    assert!(bvh.node_count() < 2 * bvh.triangle_count());
+
+    let aabb = bvh.aabb().unwrap();
+    for vertex in &vertices {
+        let [x, y, z, _] = vertex.position;
+        assert!(aabb.contain_pt((x, y, z).as_point3()));
+    }
+
+    let indices_u32: Vec<u32> = indices.iter().map(|&i| i as u32).collect();
+    let bvh_u32 = Bvh::new(&vertices, &indices_u32).unwrap();
+    assert_eq!(
+        bvh_u32.raycast_first((10.3, 7.7, 10.), (0., 0., -1.), 100.),
+        bvh.raycast_first((10.3, 7.7, 10.), (0., 0., -1.), 100.)
+    );
+
+    assert_eq!(
+        Bvh::new(&vertices, &indices[..5]).unwrap_err(),
+        BvhError::IncompleteTriangle(5)
+    );
+    assert!(matches!(
+        Bvh::new(&vertices[..10], &indices),
+        Err(BvhError::IndexOutOfBounds {
+            vertex_count: 10,
+            ..
+        })
+    ));
+    let empty = Bvh::new::<u16>(&vertices, &[]).unwrap();
+    assert!(empty.aabb().is_none());
+    assert!(
+        empty
+            .raycast_first((0., 0., 0.), (1., 0., 0.), 10.)
+            .is_none()
+    );
+    assert!(empty.query_aabb(Aabb3::new((0., 0., 0.), 10.)).is_empty());
+
+    //a vertex no triangle uses can be anything
+    let mut unused = vertices.clone();
+    unused.push(Vertex::from([f32::NAN, 0., 0., 1.]));
+    assert!(Bvh::new(&unused, &indices).is_ok());
+    unused[7].position[0] = f32::INFINITY;
+    assert!(Bvh::new(&unused, &indices).is_err());
+}
+
+#[test]
+fn test_bvh_max_depth() {
+    //over 250 orders of magnitude, each split only peels off a few triangles: 70 levels uncapped
+    let (vertices, indices): (Vec<_>, Vec<_>) = (0..250u16)
+        .map(|k| {
+            let x = 2f32.powi(k as i32 - 125);
+            (
+                [
+                    Vertex::from([x, 0., 0., 1.]),
+                    Vertex::from([x, x, 0., 1.]),
+                    Vertex::from([x, 0., x, 1.]),
+                ],
+                [3 * k, 3 * k + 1, 3 * k + 2],
+            )
+        })
+        .unzip();
+    let (vertices, indices): (Vec<_>, Vec<_>) = (
+        vertices.into_iter().flatten().collect(),
+        indices.into_iter().flatten().collect(),
+    );
+    let bvh = Bvh::new(&vertices, &indices).unwrap();
+    assert_eq!(bvh.depth(), 64);
+
+    //the deepest leaf keeps every triangle left
+    let mut found = bvh.query_aabb(bvh.aabb().unwrap());
+    found.sort();
+    assert_eq!(found, (0..250).collect::<Vec<_>>());
+    for k in 100..150 {
+        let x = 2f32.powi(k - 125);
+        let hit = bvh
+            .raycast_first((1.5 * x, 0.2 * x, 0.2 * x), (-1., 0., 0.), x)
+            .unwrap();
+        assert_eq!(hit.triangle, k as usize);
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_bvh_raycast() {
+    let (vertices, indices) = terrain(24);
+    let bvh = Bvh::new(&vertices, &indices).unwrap();
+    let mut rng = Rng::new(0x1234_5678);
+
+    for _ in 0..200 {
+        let origin = [
+            rng.range_f32(-5., 30.),
+            rng.range_f32(-5., 30.),
+            rng.range_f32(-4., 6.),
+        ];
+        let dir = [
+            rng.range_f32(-1., 1.),
+            rng.range_f32(-1., 1.),
+            rng.range_f32(-1., 1.),
+        ];
+        let max_t = rng.range_f32(1., 40.);
+
+        let expected = brute_force_raycast(&bvh, origin, dir, max_t);
+        let hits = bvh.raycast(origin, dir, max_t);
+        assert_eq!(
+            hits.iter().map(|hit| hit.t).collect::<Vec<_>>(),
+            expected.iter().map(|hit| hit.t).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            bvh.raycast_first(origin, dir, max_t).map(|hit| hit.t),
+            expected.first().map(|hit| hit.t)
+        );
+
+        //the barycentrics give back the hit point
+        for hit in hits {
+            let [a, b, c] = bvh.triangle(hit.triangle).unwrap();
+            let w = 1. - hit.u - hit.v;
+            let from_bary = [
+                w * a.x + hit.u * b.x + hit.v * c.x,
+                w * a.y + hit.u * b.y + hit.v * c.y,
+                w * a.z + hit.u * b.z + hit.v * c.z,
+            ];
+            for k in 0..3 {
+                assert!((from_bary[k] - (origin[k] + hit.t * dir[k])).abs() < 1e-3);
+            }
+        }
+    }
+
+    //straight down, through a vertex shared by 6 triangles
+    let hit = bvh
+        .raycast_first((5., 5., 10.), (0., 0., -2.), 100.)
+        .unwrap();
+    let height = (5f32 * 0.7).sin() * (5f32 * 0.5).cos() * 2.;
+    assert!((10. - 2. * hit.t - height).abs() < 1e-4);
+    //a short direction gives the same hit, further in multiples of it
+    let short = bvh
+        .raycast_first((5., 5., 10.), (0., 0., -1e-8), 1e10)
+        .unwrap();
+    assert_eq!(short.triangle, hit.triangle);
+    assert!((short.t * 1e-8 - 2. * hit.t).abs() < 1e-4);
+    assert!(
+        bvh.raycast_first((5., 5., 10.), (0., 0., 0.), 100.)
+            .is_none()
+    );
+    assert!(
+        bvh.raycast_first((5., 5., 10.), (0., 0., -1.), 1.)
+            .is_none()
+    );
+    assert!(
+        bvh.raycast_first((5., 5., 10.), (0., 0., 1.), 100.)
+            .is_none()
+    );
+}
+
+#[test]
+fn test_bvh_query_aabb() {
+    let (vertices, indices) = terrain(16);
+    let bvh = Bvh::new(&vertices, &indices).unwrap();
+
+    //above the terrain, touching nothing
+    assert!(bvh.query_aabb(Aabb3::new((8., 8., 5.), 2.)).is_empty());
+
+    //a tall box over the cell (3, 4) and its neighbours sharing an edge or a vertex
+    let mut found = bvh.query_aabb(Aabb3::new_box((3.5, 4.5, 0.), 0.25, 0.25, 10.));
+    found.sort();
+    let cell = 2 * (4 * 16 + 3);
+    assert_eq!(found, vec![cell, cell + 1]);
+
+    let mut found = bvh.query_aabb(Aabb3::new_box((3., 4., 0.), 0.5, 0.5, 10.));
+    found.sort();
+    assert_eq!(found.len(), 8);
+    assert!(found.contains(&cell));
+
+    //a thin box along the diagonal of the cell only touches the triangles on each side of it
+    let [a, _, c] = bvh.triangle(cell).unwrap();
+    let diag = Aabb3::new(((a.x + c.x) / 2., (a.y + c.y) / 2., (a.z + c.z) / 2.), 0.01);
+    let mut found = bvh.query_aabb(diag);
+    found.sort();
+    assert_eq!(found, vec![cell, cell + 1]);
+}
+
+#[test]
+fn test_bvh_refit() {
+    let (mut vertices, indices) = terrain(16);
+    let mut bvh = Bvh::new(&vertices, &indices).unwrap();
+    let before = bvh
+        .raycast_first((7.3, 2.1, 10.), (0., 0., -1.), 100.)
+        .unwrap();
+
+    for vertex in &mut vertices {
+        vertex.position[2] -= 3.;
+        vertex.position[0] += 0.5;
+    }
+    bvh.refit(&vertices).unwrap();
+    let after = bvh
+        .raycast_first((7.8, 2.1, 10.), (0., 0., -1.), 100.)
+        .unwrap();
+    assert_eq!(after.triangle, before.triangle);
+    assert!((after.t - before.t - 3.).abs() < 1e-4);
+    assert!(bvh.aabb().unwrap().max().z < 0.);
+    assert!(
+        bvh.query_aabb(Aabb3::new_box((8., 8., -3.), 8., 8., 2.5))
+            .len()
+            > 400
+    );
+
+    vertices[3].position[1] = f32::NAN;
+    assert!(matches!(
+        bvh.refit(&vertices),
+        Err(BvhError::InvalidCoord([x, y, _])) if x == 3.5 && y.is_nan()
+    ));
+    assert!(bvh.refit(&vertices[..5]).is_err());
+}
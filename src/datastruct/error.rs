@@ -0,0 +1,37 @@
+use std::fmt::Debug;
+
+use super::{
+    aabb::{Aabb, Aabb3},
+    slotmap::Handle,
+};
+
+///Error of the spatial structures, `B` being the type of their boundary and `C` the one of
+///the coordinates of a point.
+#[derive(Debug, Clone, Copy)]
+pub enum SpatialError<B, C> {
+    OutOfBoundary(B, C),
+    InvalidCoord(C),
+    InvalidHandle(Handle),
+}
+
+impl<B: Debug, C: Debug> std::fmt::Display for SpatialError<B, C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpatialError::OutOfBoundary(aabb, pt) => {
+                write!(f, "{:?} does not contains the point {:?}.", aabb, pt)
+            }
+            SpatialError::InvalidCoord(coord) => {
+                write!(f, "point of coord {:?} are invalid.", coord)
+            }
+            SpatialError::InvalidHandle(handle) => {
+                write!(f, "{:?} does not refer to an element of the tree.", handle)
+            }
+        }
+    }
+}
+
+///Error of the 2d structures.
+pub type SpatialError2d<F> = SpatialError<Aabb<F>, (F, F)>;
+
+///Error of the 3d structures.
+pub type SpatialError3d<F> = SpatialError<Aabb3<F>, (F, F, F)>;
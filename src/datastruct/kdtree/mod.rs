@@ -0,0 +1,275 @@
+#[cfg(test)]
+mod test;
+
+use std::{collections::BinaryHeap, fmt::Debug};
+
+use num::Float;
+
+use super::{
+    aabb::Aabb,
+    error::SpatialError2d,
+    nearest::{NearestEntry, NearestItem},
+    points::{As2dPoint, IndexPoint, Point},
+    shapes::{Circle, Shape2d},
+};
+
+///Static 2d tree over a set of points, built once with median splits.
+///
+///The points are stored in a single `Vec`, as an implicit balanced tree: the node of a subrange
+///is its middle point, the left subtree is before it and the right one after it.
+///The splits alternate between x (at even depths) and y. There is no empty node, unlike in a
+///[`Quadtree`](super::quadtree::Quadtree), but the points cannot be moved, added or removed.
+#[derive(Debug, Clone)]
+pub struct KdTree<F: Float + Copy + Debug, T: As2dPoint<F>> {
+    elems: Vec<T>,
+    ///the tree, `i` being the index of the element in `elems`
+    points: Vec<IndexPoint<F>>,
+    ///box containing all the points, `None` if there is none
+    boundary: Option<Aabb<F>>,
+}
+
+///subrange `lo..hi` of the points, with its depth and the corners of a box containing it
+#[derive(Debug, Clone, Copy)]
+struct SubTree<F: Float + Copy> {
+    lo: usize,
+    hi: usize,
+    depth: usize,
+    min: (F, F),
+    max: (F, F),
+}
+
+#[allow(dead_code)]
+impl<F: Float + Copy + Debug, T: As2dPoint<F>> KdTree<F, T> {
+    ///Builds the tree in O(n log n). The element `i` of `vec` is `get(i)`.
+    ///Fails if an element has invalid coordinates.
+    pub fn new(vec: Vec<T>) -> Result<Self, SpatialError2d<F>> {
+        let mut points = Vec::with_capacity(vec.len());
+        for (i, elem) in vec.iter().enumerate() {
+            let i_p = IndexPoint::new(elem.x(), elem.y(), i);
+            if !i_p.into_point().as_valid_coord() {
+                return Err(SpatialError2d::InvalidCoord((i_p.x, i_p.y)));
+            }
+            points.push(i_p);
+        }
+
+        let boundary = points.split_first().map(|(first, others)| {
+            let (min, max) = others.iter().fold(
+                ((first.x, first.y), (first.x, first.y)),
+                |(min, max), i_p| {
+                    (
+                        (min.0.min(i_p.x), min.1.min(i_p.y)),
+                        (max.0.max(i_p.x), max.1.max(i_p.y)),
+                    )
+                },
+            );
+            Aabb::from_min_max(min, max)
+        });
+        build(&mut points, 0);
+
+        Ok(Self {
+            elems: vec,
+            points,
+            boundary,
+        })
+    }
+
+    ///See `new`.
+    pub fn from_slice(slice: &[T]) -> Result<Self, SpatialError2d<F>>
+    where
+        T: Clone,
+    {
+        Self::new(slice.to_vec())
+    }
+
+    pub fn len(&self) -> usize {
+        self.elems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///Number of levels, 0 for an empty tree.
+    pub fn depth(&self) -> usize {
+        (usize::BITS - self.len().leading_zeros()) as usize
+    }
+
+    ///Smallest box containing all the elements, `None` if there is none.
+    pub fn boundary(&self) -> Option<Aabb<F>> {
+        self.boundary
+    }
+
+    ///The element `i` of the vec the tree was built from.
+    pub fn get(&self, i: usize) -> Option<&T> {
+        self.elems.get(i)
+    }
+
+    ///The elements, in the order of the vec the tree was built from.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.elems.iter()
+    }
+
+    ///Elements inside `range`, bounds included.
+    pub fn query_range(&self, range: Aabb<F>) -> Vec<&T> {
+        self.query_shape(&range)
+    }
+
+    ///Elements at most `radius` away from `center`.
+    pub fn query_circle<P: As2dPoint<F>>(&self, center: P, radius: F) -> Vec<&T> {
+        self.query_shape(&Circle::new(center, radius))
+    }
+
+    ///Elements contained in `shape`, subtrees the shape does not intersect are skipped.
+    pub fn query_shape<Sh: Shape2d<F>>(&self, shape: &Sh) -> Vec<&T> {
+        let mut result = vec![];
+        let mut stack: Vec<SubTree<F>> = self.root().into_iter().collect();
+
+        while let Some(sub) = stack.pop() {
+            if !shape.intersects_aabb(sub.aabb()) {
+                continue;
+            }
+            let (mid, left, right) = self.split(sub);
+            let i_p = self.points[mid];
+            if shape.contains_point(i_p.into_point()) {
+                result.push(&self.elems[i_p.i]);
+            }
+            stack.extend(left);
+            stack.extend(right);
+        }
+        result
+    }
+
+    ///Closest element to `point` with its distance, `None` if the tree is empty.
+    pub fn nearest<P: As2dPoint<F>>(&self, point: P) -> Option<(&T, F)> {
+        self.k_nearest(point, 1).into_iter().next()
+    }
+
+    ///The `k` closest elements to `point` with their distances, sorted by distance.
+    pub fn k_nearest<P: As2dPoint<F>>(&self, point: P, k: usize) -> Vec<(&T, F)> {
+        let point = point.as_point();
+        let mut result = Vec::with_capacity(k.min(self.len()));
+        let Some(root) = self.root() else {
+            return result;
+        };
+        if k == 0 {
+            return result;
+        }
+
+        let mut heap = BinaryHeap::new();
+        heap.push(NearestEntry {
+            key: root.dist_sq_to_pt(point),
+            item: NearestItem::Node(root),
+        });
+
+        while let Some(NearestEntry { key: dist_sq, item }) = heap.pop() {
+            match item {
+                NearestItem::Elem(i) => {
+                    result.push((&self.elems[i], dist_sq.sqrt()));
+                    if result.len() == k {
+                        break;
+                    }
+                }
+                NearestItem::Node(sub) => {
+                    let (mid, left, right) = self.split(sub);
+                    let i_p = self.points[mid];
+                    heap.push(NearestEntry {
+                        key: i_p.into_point().dist_sq(point),
+                        item: NearestItem::Elem(i_p.i),
+                    });
+                    for child in left.into_iter().chain(right) {
+                        heap.push(NearestEntry {
+                            key: child.dist_sq_to_pt(point),
+                            item: NearestItem::Node(child),
+                        });
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn root(&self) -> Option<SubTree<F>> {
+        self.boundary.map(|aabb| SubTree {
+            lo: 0,
+            hi: self.points.len(),
+            depth: 0,
+            min: (aabb.min().x, aabb.min().y),
+            max: (aabb.max().x, aabb.max().y),
+        })
+    }
+
+    ///index of the node of `sub`, and its non-empty subtrees
+    fn split(&self, sub: SubTree<F>) -> (usize, Option<SubTree<F>>, Option<SubTree<F>>) {
+        let mid = sub.lo + (sub.hi - sub.lo) / 2;
+        let pivot = self.points[mid];
+        let (min, max) = (sub.min, sub.max);
+        let (left_max, right_min) = if sub.depth.is_multiple_of(2) {
+            ((pivot.x, max.1), (pivot.x, min.1))
+        } else {
+            ((max.0, pivot.y), (min.0, pivot.y))
+        };
+
+        let left = (sub.lo < mid).then(|| SubTree {
+            lo: sub.lo,
+            hi: mid,
+            depth: sub.depth + 1,
+            min,
+            max: left_max,
+        });
+        let right = (mid + 1 < sub.hi).then(|| SubTree {
+            lo: mid + 1,
+            hi: sub.hi,
+            depth: sub.depth + 1,
+            min: right_min,
+            max,
+        });
+        (mid, left, right)
+    }
+}
+
+impl<F: Float + Copy> SubTree<F> {
+    ///the box, grown by a few ulps so that rounding its center never leaves a point out
+    fn aabb(&self) -> Aabb<F> {
+        let aabb = Aabb::from_min_max(self.min, self.max);
+        let scale =
+            aabb.center.x.abs().max(aabb.center.y.abs()) + aabb.half_width.max(aabb.half_height);
+        aabb.expand(scale * F::epsilon() * F::from(4).unwrap())
+    }
+
+    ///exact squared distance from the point to the box, 0 if the point is inside
+    fn dist_sq_to_pt(&self, point: Point<F>) -> F {
+        let dx = (self.min.0 - point.x)
+            .max(point.x - self.max.0)
+            .max(F::zero());
+        let dy = (self.min.1 - point.y)
+            .max(point.y - self.max.1)
+            .max(F::zero());
+
+        dx * dx + dy * dy
+    }
+}
+
+///puts the median of `points` (along x at even depths, y at odd ones) in the middle,
+///the points before it being lower or equal and the points after greater or equal, recursively
+fn build<F: Float + Copy>(points: &mut [IndexPoint<F>], depth: usize) {
+    if points.len() <= 1 {
+        return;
+    }
+    let mid = points.len() / 2;
+    let coord = |i_p: &IndexPoint<F>| {
+        if depth.is_multiple_of(2) {
+            i_p.x
+        } else {
+            i_p.y
+        }
+    };
+    points.select_nth_unstable_by(mid, |a, b| {
+        coord(a)
+            .partial_cmp(&coord(b))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let (left, right) = points.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
@@ -0,0 +1,116 @@
+#![cfg(test)]
+
+use crate::datastruct::{
+    aabb::Aabb,
+    kdtree::KdTree,
+    points::As2dPoint,
+    quadtree::Quadtree,
+    shapes::Circle,
+    test_util::{Rng, SEEDS, sorted_coords},
+};
+
+///random points, with some duplicates and aligned points that end up on the splits
+fn random_points(nb: usize, seed: u64) -> Vec<(f64, f64)> {
+    let mut rng = Rng::new(seed);
+    (0..nb)
+        .map(|i| {
+            if i % 10 == 0 {
+                (rng.range(-100., 100.).round(), 5.)
+            } else {
+                (rng.range(-100., 100.), rng.range(-100., 100.))
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_kdtree_range_circle() {
+    for seed in SEEDS {
+        let points = random_points(1000, seed);
+        let kdtree = KdTree::from_slice(&points).unwrap();
+        assert_eq!(kdtree.len(), 1000);
+        assert_eq!(kdtree.depth(), 10);
+        let boundary = kdtree.boundary().unwrap();
+        assert!(points.iter().all(|p| boundary.contain_pt(p.as_point())));
+
+        for range in [
+            Aabb::new((10., -20.), 35.),
+            Aabb::new_rect((-50., 5.), 60., 0.5),
+            //edges on coordinates of points
+            Aabb::from_min_max(points[3], points[7]),
+            Aabb::new((500., 500.), 10.),
+        ] {
+            let expected = points.iter().filter(|p| range.contain_pt(p.as_point()));
+            assert_eq!(
+                sorted_coords(kdtree.query_range(range)),
+                sorted_coords(expected)
+            );
+        }
+
+        let (center, radius) = ((-30., 25.), 40.);
+        let expected = points
+            .iter()
+            .filter(|p| p.as_point().dist(center.as_point()) <= radius);
+        assert_eq!(
+            sorted_coords(kdtree.query_circle(center, radius)),
+            sorted_coords(expected)
+        );
+        assert_eq!(
+            sorted_coords(kdtree.query_shape(&Circle::new(points[0], 0.))),
+            sorted_coords(points.iter().filter(|p| **p == points[0]))
+        );
+    }
+}
+
+#[test]
+fn test_kdtree_k_nearest() {
+    let points = random_points(1000, 7);
+    let kdtree = KdTree::new(points.clone()).unwrap();
+    let qtree: Quadtree<f64, (f64, f64), 4> =
+        Quadtree::new(Aabb::new((0., 0.), 100.), points.clone());
+
+    for target in [(13., 27.), (0., 5.), (-500., 800.), points[11]] {
+        let mut expected: Vec<_> = points
+            .iter()
+            .map(|p| p.as_point().dist(target.as_point()))
+            .collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let result = kdtree.k_nearest(target, 30);
+        assert_eq!(result.len(), 30);
+        for ((elem, d), e) in result.iter().zip(&expected) {
+            assert_eq!(d, e);
+            assert_eq!(elem.as_point().dist(target.as_point()), *d);
+        }
+        assert_eq!(kdtree.nearest(target).unwrap().1, expected[0]);
+        assert_eq!(
+            kdtree.nearest(target).unwrap().1,
+            qtree.nearest(target).unwrap().1
+        );
+    }
+    assert_eq!(kdtree.k_nearest((0., 0.), 5000).len(), 1000);
+    assert!(kdtree.k_nearest((0., 0.), 0).is_empty());
+}
+
+#[test]
+fn test_kdtree_edge_cases() {
+    let empty: KdTree<f64, (f64, f64)> = KdTree::new(vec![]).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(empty.depth(), 0);
+    assert!(empty.boundary().is_none());
+    assert!(empty.nearest((0., 0.)).is_none());
+    assert!(empty.query_range(Aabb::new((0., 0.), 1.)).is_empty());
+
+    let single = KdTree::new(vec![(1., 2.)]).unwrap();
+    assert_eq!(single.query_circle((1., 2.), 0.).len(), 1);
+    assert_eq!(single.nearest((4., 6.)).unwrap().1, 5.);
+
+    let same = KdTree::new(vec![(3., 3.); 50]).unwrap();
+    assert_eq!(same.query_range(Aabb::new((3., 3.), 0.1)).len(), 50);
+    assert_eq!(same.k_nearest((0., 0.), 10).len(), 10);
+
+    assert!(KdTree::new(vec![(0., 0.), (f64::NAN, 1.)]).is_err());
+    let kdtree = KdTree::new(vec![(0., 0.), (5., 1.), (2., 8.)]).unwrap();
+    assert_eq!(kdtree.get(1), Some(&(5., 1.)));
+    assert_eq!(kdtree.iter().count(), 3);
+}
@@ -0,0 +1,67 @@
+//!leaf storage shared by the point trees
+
+use arrayvec::ArrayVec;
+use num::Float;
+
+use super::points::{IndexPoint, IndexPoint3};
+
+///point carrying the index of its element in the `SlotMap` of the tree
+pub(crate) trait Indexed: Copy {
+    fn index(&self) -> usize;
+}
+
+impl<F: Float + Copy> Indexed for IndexPoint<F> {
+    #[inline(always)]
+    fn index(&self) -> usize {
+        self.i
+    }
+}
+
+impl<F: Float + Copy> Indexed for IndexPoint3<F> {
+    #[inline(always)]
+    fn index(&self) -> usize {
+        self.i
+    }
+}
+
+pub(crate) type LeafIter<'a, P> =
+    std::iter::Chain<std::slice::Iter<'a, P>, std::slice::Iter<'a, P>>;
+
+#[derive(Debug, Clone)]
+pub(crate) struct LeafData<P: Indexed, const N: usize> {
+    pub(crate) points: ArrayVec<P, N>,
+    ///points past `N` in a leaf at max depth, empty unless `points` is full
+    pub(crate) overflow: Vec<P>,
+}
+
+impl<P: Indexed, const N: usize> Default for LeafData<P, N> {
+    fn default() -> Self {
+        Self {
+            points: ArrayVec::new(),
+            overflow: vec![],
+        }
+    }
+}
+
+impl<P: Indexed, const N: usize> LeafData<P, N> {
+    #[inline(always)]
+    pub(crate) fn iter(&self) -> LeafIter<'_, P> {
+        self.points.iter().chain(self.overflow.iter())
+    }
+
+    ///keeps `points` full while there is an overflow
+    pub(crate) fn remove(&mut self, i: usize) -> bool {
+        if let Some(pos) = self.points.iter().position(|p| p.index() == i) {
+            self.points.swap_remove(pos);
+            if let Some(p) = self.overflow.pop() {
+                self.points.push(p);
+            }
+            true
+        } else if let Some(pos) = self.overflow.iter().position(|p| p.index() == i) {
+            self.overflow.swap_remove(pos);
+            true
+        } else {
+            false
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use std::fmt::Debug;
+
+use num::Float;
+
+use super::{
+    aabb::{Aabb, Bounded2d, DiagonalDirection},
+    error::SpatialError2d,
+    quadtree::DEFAULT_MAX_DEPTH,
+    slotmap::{Handle, SlotMap},
+};
+
+#[cfg(test)]
+mod test;
+
+///Quadtree storing objects with a size.
+///
+///Each node has a loose boundary twice as big as its boundary, an object is stored in the deepest
+///node containing its center whose loose boundary contains the whole object.
+///Objects too big for the children stay in the parent node, as do the objects of the nodes
+///at `max_depth`.
+#[derive(Debug, Clone)]
+pub struct LooseQuadtree<F: Float + Copy + Debug, T: Bounded2d<F>, const N: usize> {
+    elems: SlotMap<LooseElem<F, T>>,
+    base_node: LooseNode<F>,
+    max_depth: usize,
+}
+
+#[derive(Debug, Clone)]
+struct LooseElem<F: Float + Copy + Debug, T> {
+    elem: T,
+    ///the bounds the element has in the tree
+    aabb: Aabb<F>,
+}
+
+#[allow(dead_code)]
+impl<F: Float + Copy + Debug, T: Bounded2d<F>, const N: usize> LooseQuadtree<F, T, N> {
+    ///Objects centers have to be in `boundary`, the objects themselves can go over it.
+    pub fn empty(boundary: Aabb<F>) -> Self {
+        debug_assert!(N > 0, "The size should be a least 1");
+
+        Self {
+            elems: SlotMap::new(),
+            base_node: LooseNode::empty(boundary),
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    ///Maximum depth of the tree, the root being at depth 1. Panics if `max_depth` is 0.
+    ///Applies to the next insertions.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.set_max_depth(max_depth);
+        self
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        assert!(max_depth > 0, "The max depth should be a least 1");
+        self.max_depth = max_depth;
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.elems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn depth(&self) -> usize {
+        self.base_node.depth()
+    }
+
+    pub fn boundary(&self) -> Aabb<F> {
+        self.base_node.boundary
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.elems.get(handle).map(|l_e| &l_e.elem)
+    }
+
+    ///After changing the bounds of the element, call `update` to move it in the tree.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.elems.get_mut(handle).map(|l_e| &mut l_e.elem)
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.elems.contains(handle)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.elems.values().map(|l_e| &l_e.elem)
+    }
+
+    pub fn iter_with_handles(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.elems.iter().map(|(h, l_e)| (h, &l_e.elem))
+    }
+
+    pub fn insert(&mut self, elem: T) -> Result<Handle, SpatialError2d<F>> {
+        let aabb = elem.aabb();
+        self.check_bounds(aabb)?;
+
+        let handle = self.elems.insert(LooseElem { elem, aabb });
+        self.base_node.insert::<N>(
+            LooseEntry {
+                aabb,
+                i: handle.index(),
+            },
+            1,
+            self.max_depth,
+        );
+        Ok(handle)
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let l_e = self.elems.remove(handle)?;
+        let removed = self.base_node.remove::<N>(LooseEntry {
+            aabb: l_e.aabb,
+            i: handle.index(),
+        });
+        debug_assert!(
+            removed,
+            "something went wrong in LooseQuadtree::remove: the element is not in the tree"
+        );
+
+        Some(l_e.elem)
+    }
+
+    ///Moves the element of `handle` in the tree according to its current bounds.
+    ///On error, the element keeps its previous place in the tree.
+    pub fn update(&mut self, handle: Handle) -> Result<(), SpatialError2d<F>> {
+        let l_e = self
+            .elems
+            .get(handle)
+            .ok_or(SpatialError2d::InvalidHandle(handle))?;
+        let (old_aabb, new_aabb) = (l_e.aabb, l_e.elem.aabb());
+        self.check_bounds(new_aabb)?;
+
+        self.base_node.remove::<N>(LooseEntry {
+            aabb: old_aabb,
+            i: handle.index(),
+        });
+        self.base_node.insert::<N>(
+            LooseEntry {
+                aabb: new_aabb,
+                i: handle.index(),
+            },
+            1,
+            self.max_depth,
+        );
+        if let Some(l_e) = self.elems.get_mut(handle) {
+            l_e.aabb = new_aabb;
+        }
+        Ok(())
+    }
+
+    ///Elements whose bounds intersect `range`.
+    pub fn query_overlap(&self, range: Aabb<F>) -> Vec<&T> {
+        self.query_overlap_handles(range)
+            .into_iter()
+            .filter_map(|h| self.get(h))
+            .collect()
+    }
+
+    pub fn query_overlap_handles(&self, range: Aabb<F>) -> Vec<Handle> {
+        let mut entries = vec![];
+        self.base_node.query_overlap(range, &mut entries);
+
+        entries
+            .into_iter()
+            .filter_map(|entry| self.elems.handle_at(entry.i))
+            .collect()
+    }
+
+    ///Every pair of elements whose bounds intersect, each pair is given once.
+    pub fn overlapping_pairs(&self) -> Vec<(Handle, Handle)> {
+        let mut pairs = vec![];
+        self.base_node.collect_pairs(&mut pairs);
+
+        pairs
+            .into_iter()
+            .filter_map(|(i, j)| Some((self.elems.handle_at(i)?, self.elems.handle_at(j)?)))
+            .collect()
+    }
+
+    ///Calls `map` on every pair of elements whose bounds intersect, each pair is given once.
+    ///The tree is not updated, call `update` for the elements whose bounds changed.
+    pub fn map_overlapping_pairs(&mut self, map: impl Fn(&mut T, &mut T)) {
+        let mut pairs = vec![];
+        self.base_node.collect_pairs(&mut pairs);
+
+        for (i, j) in pairs {
+            if let Some((a, b)) = self.elems.get2_mut_by_index(i, j) {
+                map(&mut a.elem, &mut b.elem);
+            }
+        }
+    }
+
+    fn check_bounds(&self, aabb: Aabb<F>) -> Result<(), SpatialError2d<F>> {
+        let center = aabb.center;
+        if !center.as_valid_coord() {
+            return Err(SpatialError2d::InvalidCoord((center.x, center.y)));
+        }
+        if !self.base_node.boundary.contain_pt(center) {
+            return Err(SpatialError2d::OutOfBoundary(
+                self.base_node.boundary,
+                (center.x, center.y),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LooseEntry<F: Float + Copy + Debug> {
+    aabb: Aabb<F>,
+    i: usize,
+}
+
+#[derive(Debug, Clone)]
+struct LooseNode<F: Float + Copy + Debug> {
+    boundary: Aabb<F>,
+    entries: Vec<LooseEntry<F>>,
+    children: Option<Box<[LooseNode<F>; 4]>>,
+}
+
+impl<F: Float + Copy + Debug> LooseNode<F> {
+    fn empty(boundary: Aabb<F>) -> Self {
+        Self {
+            boundary,
+            entries: vec![],
+            children: None,
+        }
+    }
+
+    #[inline(always)]
+    fn loose_boundary(&self) -> Aabb<F> {
+        let two = F::one() + F::one();
+        Aabb {
+            center: self.boundary.center,
+            half_width: self.boundary.half_width * two,
+            half_height: self.boundary.half_height * two,
+        }
+    }
+
+    ///index of the child containing the center of `aabb`, if the whole `aabb` fits in its loose boundary
+    #[inline(always)]
+    fn fitting_child(&self, aabb: Aabb<F>) -> Option<usize> {
+        let two = F::one() + F::one();
+        let fits = aabb.half_width <= self.boundary.half_width / two
+            && aabb.half_height <= self.boundary.half_height / two;
+
+        fits.then(|| subdivide_index(self.boundary.diag_pos_from_center(aabb.center)))
+    }
+
+    ///`depth` counts this node, the nodes at `max_depth` are not subdivided
+    fn insert<const N: usize>(&mut self, entry: LooseEntry<F>, depth: usize, max_depth: usize) {
+        let fitting_child = self.fitting_child(entry.aabb);
+        match (&mut self.children, fitting_child) {
+            (Some(children), Some(index)) => {
+                children[index].insert::<N>(entry, depth + 1, max_depth)
+            }
+            _ => {
+                self.entries.push(entry);
+                if self.children.is_none() && self.entries.len() > N && depth < max_depth {
+                    self.subdivide::<N>(depth, max_depth);
+                }
+            }
+        }
+    }
+
+    fn subdivide<const N: usize>(&mut self, depth: usize, max_depth: usize) {
+        self.children = Some(Box::new(self.boundary.subdivide().map(LooseNode::empty)));
+
+        for entry in std::mem::take(&mut self.entries) {
+            self.insert::<N>(entry, depth, max_depth);
+        }
+    }
+
+    ///removes the entry, looking along the path of its center, and merges the nodes left under capacity
+    fn remove<const N: usize>(&mut self, entry: LooseEntry<F>) -> bool {
+        if let Some(pos) = self.entries.iter().position(|e| e.i == entry.i) {
+            self.entries.swap_remove(pos);
+            self.try_merge::<N>();
+            return true;
+        }
+
+        let dir = self.boundary.diag_pos_from_center(entry.aabb.center);
+        let removed = match &mut self.children {
+            Some(children) => children[subdivide_index(dir)].remove::<N>(entry),
+            None => false,
+        };
+        if removed {
+            self.try_merge::<N>();
+        }
+        removed
+    }
+
+    fn try_merge<const N: usize>(&mut self) {
+        let Some(children) = &mut self.children else {
+            return;
+        };
+        if children.iter().any(|child| child.children.is_some()) {
+            return;
+        }
+        let total = self.entries.len()
+            + children
+                .iter()
+                .map(|child| child.entries.len())
+                .sum::<usize>();
+        if total > N {
+            return;
+        }
+
+        for child in children.iter_mut() {
+            self.entries.append(&mut child.entries);
+        }
+        self.children = None;
+    }
+
+    fn query_overlap(&self, range: Aabb<F>, result: &mut Vec<LooseEntry<F>>) {
+        let mut stack = vec![(self, true)];
+
+        while let Some((curr_node, is_root)) = stack.pop() {
+            //the root also holds the objects going over its loose boundary
+            if !is_root && !curr_node.loose_boundary().intersect(range) {
+                continue;
+            }
+            result.extend(
+                curr_node
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.aabb.intersect(range)),
+            );
+            if let Some(children) = &curr_node.children {
+                stack.extend(children.iter().map(|child| (child, false)));
+            }
+        }
+    }
+
+    ///loose boundaries of siblings overlap, so every entry is queried against the whole tree
+    fn collect_pairs(&self, pairs: &mut Vec<(usize, usize)>) {
+        let mut overlapping = vec![];
+        let mut stack = vec![self];
+
+        while let Some(curr_node) = stack.pop() {
+            for entry in &curr_node.entries {
+                overlapping.clear();
+                self.query_overlap(entry.aabb, &mut overlapping);
+                pairs.extend(
+                    overlapping
+                        .iter()
+                        .filter(|other| other.i > entry.i)
+                        .map(|other| (entry.i, other.i)),
+                );
+            }
+            if let Some(children) = &curr_node.children {
+                stack.extend(children.iter());
+            }
+        }
+    }
+
+    fn depth(&self) -> usize {
+        1 + self
+            .children
+            .as_ref()
+            .map(|children| children.iter().map(LooseNode::depth).max().unwrap_or(0))
+            .unwrap_or(0)
+    }
+}
+
+///position of a child in the array returned by `Aabb::subdivide`: up-left, up-right, down-right
+///then down-left, unlike the children of a `Quadtree` which start up-right
+#[inline(always)]
+fn subdivide_index(dir: DiagonalDirection) -> usize {
+    match dir {
+        DiagonalDirection::UpLeft => 0,
+        DiagonalDirection::UpRight => 1,
+        DiagonalDirection::DownRight => 2,
+        DiagonalDirection::DownLeft => 3,
+    }
+}
@@ -0,0 +1,205 @@
+#![cfg(test)]
+
+use crate::datastruct::{
+    aabb::{Aabb, Bounded2d},
+    loose_quadtree::LooseQuadtree,
+    slotmap::Handle,
+};
+
+#[derive(Debug, Clone)]
+struct Sprite {
+    x: f32,
+    y: f32,
+    size: f32,
+}
+
+impl Bounded2d<f32> for Sprite {
+    fn aabb(&self) -> Aabb<f32> {
+        Aabb::new((self.x, self.y), self.size)
+    }
+}
+
+fn sprites(nb: usize) -> Vec<Sprite> {
+    (0..nb)
+        .map(|i| {
+            let t = i as f32 * 0.37;
+            Sprite {
+                x: t.cos() * t,
+                y: t.sin() * t,
+                size: 0.5 + (i % 7) as f32 * (1. + (i % 50 == 0) as u8 as f32 * 20.),
+            }
+        })
+        .collect()
+}
+
+fn sorted_pairs(mut pairs: Vec<(usize, usize)>) -> Vec<(usize, usize)> {
+    for pair in &mut pairs {
+        if pair.0 > pair.1 {
+            *pair = (pair.1, pair.0);
+        }
+    }
+    pairs.sort();
+    pairs
+}
+
+fn build(sprites: &[Sprite]) -> (LooseQuadtree<f32, Sprite, 4>, Vec<Handle>) {
+    let mut tree = LooseQuadtree::empty(Aabb::new((0., 0.), 200.));
+    let handles = sprites
+        .iter()
+        .map(|s| tree.insert(s.clone()).unwrap())
+        .collect();
+    (tree, handles)
+}
+
+#[test]
+fn test_loose_quadtree_insert() {
+    let (tree, handles) = build(&sprites(300));
+
+    assert_eq!(tree.len(), 300);
+    assert!(tree.depth() > 1);
+    assert_eq!(tree.get(handles[12]).unwrap().x, sprites(300)[12].x);
+
+    let mut tree = tree;
+    assert!(
+        tree.insert(Sprite {
+            x: 300.,
+            y: 0.,
+            size: 1.
+        })
+        .is_err()
+    );
+    //the center has to be in the boundary, not the whole object
+    assert!(
+        tree.insert(Sprite {
+            x: 199.,
+            y: 0.,
+            size: 50.
+        })
+        .is_ok()
+    );
+}
+
+#[test]
+fn test_loose_quadtree_query_overlap() {
+    let sprites = sprites(300);
+    let (tree, handles) = build(&sprites);
+
+    for range in [
+        Aabb::new((0., 0.), 10.),
+        Aabb::new((-50., 30.), 25.),
+        Aabb::from_min_max((-100., -5.), (100., 10.)),
+    ] {
+        let mut expected: Vec<usize> = (0..sprites.len())
+            .filter(|i| sprites[*i].aabb().intersect(range))
+            .collect();
+        let mut found: Vec<usize> = tree
+            .query_overlap_handles(range)
+            .into_iter()
+            .map(|h| handles.iter().position(|o| *o == h).unwrap())
+            .collect();
+        expected.sort();
+        found.sort();
+
+        assert!(!expected.is_empty());
+        assert_eq!(found, expected);
+        assert_eq!(tree.query_overlap(range).len(), expected.len());
+    }
+}
+
+#[test]
+fn test_loose_quadtree_pairs() {
+    let sprites = sprites(300);
+    let (tree, handles) = build(&sprites);
+
+    let mut expected = vec![];
+    for i in 0..sprites.len() {
+        for j in i + 1..sprites.len() {
+            if sprites[i].aabb().intersect(sprites[j].aabb()) {
+                expected.push((i, j));
+            }
+        }
+    }
+
+    let index = |h: Handle| handles.iter().position(|o| *o == h).unwrap();
+    let found = tree
+        .overlapping_pairs()
+        .into_iter()
+        .map(|(a, b)| (index(a), index(b)))
+        .collect();
+
+    assert!(!expected.is_empty());
+    assert_eq!(sorted_pairs(found), sorted_pairs(expected));
+}
+
+#[test]
+fn test_loose_quadtree_map_pairs() {
+    let sprites = sprites(100);
+    let (mut tree, handles) = build(&sprites);
+
+    let pair_nb = tree.overlapping_pairs().len();
+    tree.map_overlapping_pairs(|a, b| {
+        a.size += 1000.;
+        b.size += 1000.;
+    });
+    let total_growth: f32 = handles
+        .iter()
+        .zip(&sprites)
+        .map(|(h, s)| tree.get(*h).unwrap().size - s.size)
+        .sum();
+
+    assert_eq!(total_growth, pair_nb as f32 * 2000.);
+}
+
+#[test]
+fn test_loose_quadtree_remove_update() {
+    let sprites = sprites(300);
+    let (mut tree, handles) = build(&sprites);
+
+    for h in &handles[..150] {
+        assert!(tree.remove(*h).is_some());
+    }
+    assert!(tree.remove(handles[0]).is_none());
+    assert_eq!(tree.len(), 150);
+
+    let range = Aabb::new((150., 150.), 1.);
+    assert!(tree.query_overlap(range).is_empty());
+
+    let sprite = tree.get_mut(handles[200]).unwrap();
+    sprite.x = 150.;
+    sprite.y = 150.;
+    sprite.size = 0.5;
+    tree.update(handles[200]).unwrap();
+    assert_eq!(tree.query_overlap_handles(range), vec![handles[200]]);
+
+    for h in &handles[150..] {
+        tree.remove(*h);
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.depth(), 1);
+}
+
+#[test]
+fn test_loose_quadtree_max_depth() {
+    let mut tree: LooseQuadtree<f32, Sprite, 4> =
+        LooseQuadtree::empty(Aabb::new((0., 0.), 200.)).with_max_depth(3);
+    assert_eq!(tree.max_depth(), 3);
+
+    //coincident objects would subdivide forever
+    let handles: Vec<_> = (0..50)
+        .map(|_| {
+            tree.insert(Sprite {
+                x: 10.,
+                y: 10.,
+                size: 0.1,
+            })
+            .unwrap()
+        })
+        .collect();
+    assert_eq!(tree.depth(), 3);
+    assert_eq!(tree.query_overlap(Aabb::new((10., 10.), 1.)).len(), 50);
+
+    for h in handles {
+        assert!(tree.remove(h).is_some());
+    }
+    assert_eq!(tree.depth(), 1);
+}
@@ -1,3 +1,20 @@
+#![forbid(unsafe_code)]
+
 pub mod aabb;
+pub mod bvh;
+pub mod error;
+pub mod kdtree;
+mod leaf;
+pub mod loose_quadtree;
+mod nearest;
+pub mod octree;
 pub mod points;
 pub mod quadtree;
+pub mod rtree;
+pub mod shapes;
+pub mod slotmap;
+pub mod spatial_hash;
+pub mod spatial_index;
+#[cfg(test)]
+pub(crate) mod test_util;
+mod util;
@@ -0,0 +1,38 @@
+//!min-heap entries for the best-first searches of the trees
+
+use std::{cmp::Ordering, fmt::Debug};
+
+use num::Float;
+
+///`Nd` refers to a node, a reference or an index in an arena
+pub(crate) enum NearestItem<Nd> {
+    Node(Nd),
+    Elem(usize),
+}
+
+///min-heap entry, ordered by key (a distance)
+pub(crate) struct NearestEntry<F: Float + Copy + Debug, Nd> {
+    pub(crate) key: F,
+    pub(crate) item: NearestItem<Nd>,
+}
+
+impl<F: Float + Copy + Debug, Nd> PartialEq for NearestEntry<F, Nd> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<F: Float + Copy + Debug, Nd> Eq for NearestEntry<F, Nd> {}
+
+impl<F: Float + Copy + Debug, Nd> PartialOrd for NearestEntry<F, Nd> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<F: Float + Copy + Debug, Nd> Ord for NearestEntry<F, Nd> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        //reversed, BinaryHeap is a max-heap
+        other.key.partial_cmp(&self.key).unwrap_or(Ordering::Equal)
+    }
+}
@@ -0,0 +1,457 @@
+#[cfg(test)]
+mod test;
+
+use std::{collections::BinaryHeap, fmt::Debug};
+
+use arrayvec::ArrayVec;
+use num::Float;
+
+pub use super::aabb::Aabb3;
+pub use super::points::{As3dPoint, IndexPoint3, Point3};
+use super::{
+    error::SpatialError3d,
+    leaf::LeafData,
+    nearest::{NearestEntry, NearestItem},
+    quadtree::DEFAULT_MAX_DEPTH,
+    slotmap::{Handle, SlotMap},
+    util::grown_bounds,
+};
+
+///Point octree with `N` elements per leaf, the 3d counterpart of
+///[`Quadtree`](super::quadtree::Quadtree).
+///
+///The elements are stored in a `SlotMap`, the handles returned by `insert` stay valid until the
+///element is removed.
+#[derive(Debug, Clone)]
+pub struct Octree<F: Float + Copy + Debug, T: As3dPoint<F>, const N: usize> {
+    elems: SlotMap<T>,
+    base_node: OctNode<F, N>,
+    max_depth: usize,
+}
+
+#[derive(Debug, Clone)]
+struct OctNode<F: Float + Copy + Debug, const N: usize> {
+    boundary: Aabb3<F>,
+    data: OctNodeData<F, N>,
+}
+
+#[derive(Debug, Clone)]
+enum OctNodeData<F: Float + Copy + Debug, const N: usize> {
+    ///children in the order of `Aabb3::subdivide`
+    Child(Box<[OctNode<F, N>; 8]>),
+    Leaf(OctLeafData<F, N>),
+}
+
+type OctLeafData<F, const N: usize> = LeafData<IndexPoint3<F>, N>;
+
+pub type OctreeError<F> = SpatialError3d<F>;
+
+#[allow(dead_code)]
+impl<F: Float + Copy + Debug, T: As3dPoint<F>, const N: usize> Octree<F, T, N> {
+    pub fn empty(boundary: Aabb3<F>) -> Self {
+        debug_assert!(N > 0, "The size should be a least 1");
+
+        Self {
+            elems: SlotMap::new(),
+            base_node: OctNode::leaf(boundary),
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    ///The element `i` of `vec` can be found using the `i`-th handle of `iter_with_handles`.
+    ///`boundary` grows to contain all the elements.
+    ///
+    ///# Panics
+    ///
+    ///If a coordinate is NaN or infinite, see `try_new`.
+    pub fn new(boundary: Aabb3<F>, vec: Vec<T>) -> Self {
+        Self::try_new(boundary, vec)
+            .expect("Octree::new: the elements should not have NaN or infinite coordinates")
+    }
+
+    ///Like `new`, with an error if a coordinate is NaN or infinite.
+    pub fn try_new(boundary: Aabb3<F>, vec: Vec<T>) -> Result<Self, OctreeError<F>> {
+        debug_assert!(N > 0, "The size should be a least 1");
+
+        let elems: SlotMap<T> = vec.into();
+        let mut result = Self {
+            base_node: OctNode::leaf(fitting_boundary(boundary, &elems)),
+            elems,
+            max_depth: DEFAULT_MAX_DEPTH,
+        };
+        result.rebuild()?;
+        Ok(result)
+    }
+
+    ///Maximum depth of the tree, the root being at depth 1. Panics if `max_depth` is 0.
+    ///Applies to the next insertions, call `rebuild` to apply it to the whole tree.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.set_max_depth(max_depth);
+        self
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        assert!(max_depth > 0, "The max depth should be a least 1");
+        self.max_depth = max_depth;
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.elems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn depth(&self) -> usize {
+        self.base_node.depth()
+    }
+
+    pub fn boundary(&self) -> Aabb3<F> {
+        self.base_node.boundary
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.elems.get(handle)
+    }
+
+    ///After moving the element, call `update_position` to move it in the tree.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.elems.get_mut(handle)
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.elems.contains(handle)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.elems.values()
+    }
+
+    pub fn iter_with_handles(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.elems.iter()
+    }
+
+    pub fn insert(&mut self, elem: T) -> Result<Handle, OctreeError<F>> {
+        let i_p = IndexPoint3::new(elem.x(), elem.y(), elem.z(), usize::MAX);
+        self.check_point(i_p)?;
+
+        let handle = self.elems.insert(elem);
+        self.base_node.insert(
+            IndexPoint3 {
+                i: handle.index(),
+                ..i_p
+            },
+            self.max_depth,
+        );
+        Ok(handle)
+    }
+
+    ///Removes the element of `handle` and returns it, `None` if the handle is no longer valid.
+    ///Other handles stay valid.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let elem = self.elems.remove(handle)?;
+
+        let i_p = self
+            .base_node
+            .find(handle.index(), elem.as_point3())
+            .expect("something went wrong in Octree::remove: the element is not in the tree");
+        self.base_node.remove(i_p);
+        Some(elem)
+    }
+
+    ///Moves the element of `handle` in the tree to its current position,
+    ///to call after modifying it through `get_mut`.
+    ///On error, the element keeps its previous place in the tree.
+    pub fn update_position(&mut self, handle: Handle) -> Result<(), OctreeError<F>> {
+        let elem = self
+            .elems
+            .get(handle)
+            .ok_or(OctreeError::InvalidHandle(handle))?;
+        let new_i_p = IndexPoint3::new(elem.x(), elem.y(), elem.z(), handle.index());
+        self.check_point(new_i_p)?;
+
+        let old_i_p = self
+            .base_node
+            .find(handle.index(), new_i_p.into_point())
+            .expect(
+                "something went wrong in Octree::update_position: the element is not in the tree",
+            );
+        self.base_node.remove(old_i_p);
+        self.base_node.insert(new_i_p, self.max_depth);
+        Ok(())
+    }
+
+    ///Rebuilds the nodes from the elements, fails if an element is out of the boundary.
+    pub fn rebuild(&mut self) -> Result<(), OctreeError<F>> {
+        let mut points = Vec::with_capacity(self.elems.len());
+        for (handle, elem) in self.elems.iter() {
+            let i_p = IndexPoint3::new(elem.x(), elem.y(), elem.z(), handle.index());
+            self.check_point(i_p)?;
+            points.push(i_p);
+        }
+
+        self.base_node = OctNode::leaf(self.boundary());
+        for i_p in points {
+            self.base_node.insert(i_p, self.max_depth);
+        }
+        Ok(())
+    }
+
+    ///Elements inside `range`, bounds included.
+    pub fn query_range(&self, range: Aabb3<F>) -> Vec<&T> {
+        let mut result = vec![];
+        let mut stack = vec![&self.base_node];
+
+        while let Some(curr_node) = stack.pop() {
+            if !curr_node.boundary.intersect(range) {
+                continue;
+            }
+            match &curr_node.data {
+                OctNodeData::Child(children) => stack.extend(children.iter()),
+                OctNodeData::Leaf(leaf) => result.extend(
+                    leaf.iter()
+                        .filter(|i_p| range.contain_pt(i_p.into_point()))
+                        .map(|i_p| self.elem_at(i_p.i)),
+                ),
+            }
+        }
+        result
+    }
+
+    ///Elements at most `radius` away from `center`.
+    pub fn query_sphere<P: As3dPoint<F>>(&self, center: P, radius: F) -> Vec<&T> {
+        let center = center.as_point3();
+        let radius_sq = radius * radius;
+        let mut result = vec![];
+        let mut stack = vec![&self.base_node];
+
+        while let Some(curr_node) = stack.pop() {
+            if curr_node.boundary.dist_sq_to_pt(center) > radius_sq {
+                continue;
+            }
+            match &curr_node.data {
+                OctNodeData::Child(children) => stack.extend(children.iter()),
+                OctNodeData::Leaf(leaf) => result.extend(
+                    leaf.iter()
+                        .filter(|i_p| i_p.into_point().dist_sq(center) <= radius_sq)
+                        .map(|i_p| self.elem_at(i_p.i)),
+                ),
+            }
+        }
+        result
+    }
+
+    ///Closest element to `point` with its distance, `None` if the tree is empty.
+    pub fn nearest<P: As3dPoint<F>>(&self, point: P) -> Option<(&T, F)> {
+        self.k_nearest(point, 1).into_iter().next()
+    }
+
+    ///The `k` closest elements to `point` with their distances, sorted by distance.
+    pub fn k_nearest<P: As3dPoint<F>>(&self, point: P, k: usize) -> Vec<(&T, F)> {
+        let point = point.as_point3();
+        let mut result = Vec::with_capacity(k);
+        if k == 0 {
+            return result;
+        }
+
+        let mut heap = BinaryHeap::new();
+        heap.push(NearestEntry {
+            key: self.base_node.boundary.dist_sq_to_pt(point),
+            item: NearestItem::Node(&self.base_node),
+        });
+
+        while let Some(NearestEntry { key: dist_sq, item }) = heap.pop() {
+            match item {
+                NearestItem::Elem(i) => {
+                    result.push((self.elem_at(i), dist_sq.sqrt()));
+                    if result.len() == k {
+                        break;
+                    }
+                }
+                NearestItem::Node(node) => match &node.data {
+                    OctNodeData::Child(children) => {
+                        for child in children.iter() {
+                            heap.push(NearestEntry {
+                                key: child.boundary.dist_sq_to_pt(point),
+                                item: NearestItem::Node(child),
+                            });
+                        }
+                    }
+                    OctNodeData::Leaf(leaf) => {
+                        for i_p in leaf.iter() {
+                            heap.push(NearestEntry {
+                                key: i_p.into_point().dist_sq(point),
+                                item: NearestItem::Elem(i_p.i),
+                            });
+                        }
+                    }
+                },
+            }
+        }
+        result
+    }
+
+    fn check_point(&self, i_p: IndexPoint3<F>) -> Result<(), OctreeError<F>> {
+        if !i_p.into_point().as_valid_coord() {
+            return Err(OctreeError::InvalidCoord((i_p.x, i_p.y, i_p.z)));
+        }
+        if !self.boundary().contain_pt(i_p.into_point()) {
+            return Err(OctreeError::OutOfBoundary(
+                self.boundary(),
+                (i_p.x, i_p.y, i_p.z),
+            ));
+        }
+        Ok(())
+    }
+
+    ///element of a slot referenced by the tree
+    #[inline(always)]
+    fn elem_at(&self, i: usize) -> &T {
+        self.elems
+            .get_by_index(i)
+            .expect("something went wrong in Octree: the tree refers to an empty slot")
+    }
+}
+
+impl<F: Float + Copy + Debug, const N: usize> OctNode<F, N> {
+    fn leaf(boundary: Aabb3<F>) -> Self {
+        Self {
+            boundary,
+            data: OctNodeData::Leaf(OctLeafData::default()),
+        }
+    }
+
+    ///the point should be in the boundary
+    fn insert(&mut self, i_p: IndexPoint3<F>, max_depth: usize) {
+        let mut curr = self;
+        let mut depth = 1;
+
+        loop {
+            let boundary = curr.boundary;
+            match &mut curr.data {
+                OctNodeData::Child(_) => {}
+                OctNodeData::Leaf(leaf) => {
+                    if !leaf.points.is_full() {
+                        leaf.points.push(i_p);
+                        return;
+                    } else if depth >= max_depth || !leaf.overflow.is_empty() {
+                        leaf.overflow.push(i_p);
+                        return;
+                    }
+                    let leaf = std::mem::take(leaf);
+                    let mut children = Box::new(boundary.subdivide().map(OctNode::leaf));
+                    for old in leaf.iter() {
+                        if let OctNodeData::Leaf(child_leaf) =
+                            &mut children[boundary.octant(old.into_point())].data
+                        {
+                            child_leaf.points.push(*old);
+                        }
+                    }
+                    curr.data = OctNodeData::Child(children);
+                }
+            }
+            let OctNodeData::Child(children) = &mut curr.data else {
+                unreachable!()
+            };
+            curr = &mut children[boundary.octant(i_p.into_point())];
+            depth += 1;
+        }
+    }
+
+    ///looks for the point of index `i`, first in the leaf containing `hint`, then everywhere
+    fn find(&self, i: usize, hint: Point3<F>) -> Option<IndexPoint3<F>> {
+        let mut curr = self;
+        while let OctNodeData::Child(children) = &curr.data {
+            curr = &children[curr.boundary.octant(hint)];
+        }
+        if let OctNodeData::Leaf(leaf) = &curr.data
+            && let Some(i_p) = leaf.iter().find(|i_p| i_p.i == i)
+        {
+            return Some(*i_p);
+        }
+
+        let mut stack = vec![self];
+        while let Some(curr) = stack.pop() {
+            match &curr.data {
+                OctNodeData::Child(children) => stack.extend(children.iter()),
+                OctNodeData::Leaf(leaf) => {
+                    if let Some(i_p) = leaf.iter().find(|i_p| i_p.i == i) {
+                        return Some(*i_p);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    ///removes the point `i_p` (found with its stored coordinates), merging the nodes left under capacity
+    fn remove(&mut self, i_p: IndexPoint3<F>) -> bool {
+        let boundary = self.boundary;
+        let removed = match &mut self.data {
+            OctNodeData::Leaf(leaf) => return leaf.remove(i_p.i),
+            OctNodeData::Child(children) => children[boundary.octant(i_p.into_point())].remove(i_p),
+        };
+        if removed {
+            self.try_merge();
+        }
+        removed
+    }
+
+    ///turns the node back into a leaf if its children are leaves holding at most N points
+    fn try_merge(&mut self) {
+        let OctNodeData::Child(children) = &self.data else {
+            return;
+        };
+        let mut points = ArrayVec::new();
+        for child in children.iter() {
+            match &child.data {
+                OctNodeData::Leaf(leaf) => {
+                    if !leaf.overflow.is_empty()
+                        || points.try_extend_from_slice(&leaf.points).is_err()
+                    {
+                        return;
+                    }
+                }
+                OctNodeData::Child(_) => return,
+            }
+        }
+        self.data = OctNodeData::Leaf(OctLeafData {
+            points,
+            overflow: vec![],
+        });
+    }
+
+    fn depth(&self) -> usize {
+        1 + match &self.data {
+            OctNodeData::Child(children) => children
+                .iter()
+                .map(|child| child.depth())
+                .max()
+                .unwrap_or(0),
+            OctNodeData::Leaf(_) => 0,
+        }
+    }
+}
+
+///`boundary` grown by a margin of 1 to contain all the elements, if it does not already
+fn fitting_boundary<F: Float + Copy + Debug, T: As3dPoint<F>>(
+    boundary: Aabb3<F>,
+    elems: &SlotMap<T>,
+) -> Aabb3<F> {
+    if elems.values().all(|p| boundary.contain_pt(p.as_point3())) {
+        return boundary;
+    }
+    let (min, max) = (boundary.min(), boundary.max());
+    let (min, max) = grown_bounds(
+        [min.x, min.y, min.z],
+        [max.x, max.y, max.z],
+        elems.values().map(|elem| [elem.x(), elem.y(), elem.z()]),
+    );
+
+    Aabb3::from_min_max((min[0], min[1], min[2]), (max[0], max[1], max[2]))
+}
@@ -0,0 +1,148 @@
+#![cfg(test)]
+
+use crate::datastruct::{
+    octree::{Aabb3, As3dPoint, Octree, Point3},
+    test_util::sorted_coords3,
+};
+
+///points on a helix climbing along z
+fn helix_points(n: usize) -> Vec<(f64, f64, f64)> {
+    (0..n)
+        .map(|i| {
+            let t = i as f64 * 0.37;
+            let r = 5. + i as f64 * 0.3;
+            (r * t.cos(), r * t.sin(), i as f64 * 0.4 - 100.)
+        })
+        .collect()
+}
+
+#[test]
+fn test_octree_query_range_sphere() {
+    let points = helix_points(500);
+    let tree: Octree<f64, (f64, f64, f64), 4> =
+        Octree::new(Aabb3::new((0., 0., 0.), 50.), points.clone());
+    assert_eq!(tree.len(), 500);
+    assert!(tree.depth() > 1);
+    //the boundary grew to fit the points
+    assert!(
+        points
+            .iter()
+            .all(|p| tree.boundary().contain_pt(p.as_point3()))
+    );
+    assert!(tree.boundary().contain_aabb(Aabb3::new((0., 0., 0.), 50.)));
+
+    let mut invalid = points.clone();
+    invalid[7].1 = f64::NAN;
+    assert!(Octree::<f64, _, 4>::try_new(Aabb3::new((0., 0., 0.), 50.), invalid).is_err());
+
+    for range in [
+        Aabb3::new((0., 0., 0.), 30.),
+        Aabb3::new_box((-50., 40., -20.), 60., 20., 35.),
+        Aabb3::new((150., -150., 90.), 80.),
+    ] {
+        let expected = points.iter().filter(|p| range.contain_pt(p.as_point3()));
+        assert_eq!(
+            sorted_coords3(tree.query_range(range)),
+            sorted_coords3(expected)
+        );
+    }
+
+    let center = Point3 {
+        x: 20.,
+        y: -40.,
+        z: 10.,
+    };
+    let expected = points
+        .iter()
+        .filter(|p| p.as_point3().dist_sq(center) <= 45. * 45.);
+    assert_eq!(
+        sorted_coords3(tree.query_sphere(center, 45.)),
+        sorted_coords3(expected)
+    );
+}
+
+#[test]
+fn test_octree_k_nearest() {
+    let points = helix_points(400);
+    let tree: Octree<f64, (f64, f64, f64), 8> =
+        Octree::new(Aabb3::new((0., 0., 0.), 200.), points.clone());
+    assert!(tree.nearest((0., 0., 0.)).is_some());
+
+    let target = (13., 27., -40.).as_point3();
+    let mut expected: Vec<_> = points.iter().map(|p| p.as_point3().dist(target)).collect();
+    expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let result = tree.k_nearest(target, 25);
+    assert_eq!(result.len(), 25);
+    for ((elem, d), e) in result.iter().zip(&expected) {
+        assert!((d - e).abs() < 1e-9);
+        assert!((elem.as_point3().dist(target) - d).abs() < 1e-9);
+    }
+    assert_eq!(tree.nearest(target).unwrap().1, result[0].1);
+    assert_eq!(tree.k_nearest(target, 1000).len(), 400);
+    assert!(tree.k_nearest(target, 0).is_empty());
+
+    let empty: Octree<f64, (f64, f64, f64), 8> = Octree::empty(Aabb3::new((0., 0., 0.), 1.));
+    assert!(empty.nearest(target).is_none());
+}
+
+#[test]
+fn test_octree_insert_remove_update() {
+    let mut tree: Octree<f64, [f64; 3], 4> = Octree::empty(Aabb3::new((0., 0., 0.), 200.));
+    let points = helix_points(300);
+    let handles: Vec<_> = points
+        .iter()
+        .map(|&(x, y, z)| tree.insert([x, y, z]).unwrap())
+        .collect();
+    assert!(tree.depth() > 1);
+    assert!(tree.insert([500., 0., 0.]).is_err());
+    assert!(tree.insert([f64::NAN, 0., 0.]).is_err());
+
+    for handle in &handles[..200] {
+        assert!(tree.remove(*handle).is_some());
+        assert!(tree.remove(*handle).is_none());
+    }
+    assert_eq!(tree.len(), 100);
+    assert_eq!(tree.query_range(tree.boundary()).len(), 100);
+
+    //mirror the remaining points through the z axis
+    for handle in &handles[200..] {
+        let p = tree.get_mut(*handle).unwrap();
+        (p[0], p[1]) = (-p[0], -p[1]);
+        tree.update_position(*handle).unwrap();
+    }
+    let range = Aabb3::new((-20., 10., 0.), 40.);
+    let expected = points[200..]
+        .iter()
+        .map(|&(x, y, z)| [-x, -y, z])
+        .filter(|p| range.contain_pt(p.as_point3()))
+        .count();
+    assert_eq!(tree.query_range(range).len(), expected);
+
+    tree.get_mut(handles[250]).unwrap()[2] = 1000.;
+    assert!(tree.update_position(handles[250]).is_err());
+    assert!(tree.update_position(handles[0]).is_err());
+
+    tree.get_mut(handles[250]).unwrap()[2] = 0.;
+    for handle in &handles[200..] {
+        tree.remove(*handle);
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.depth(), 1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_octree_coincident_points() {
+    let mut tree: Octree<f32, (f32, f32, f32), 4> =
+        Octree::empty(Aabb3::new((0., 0., 0.), 100.)).with_max_depth(5);
+    for _ in 0..1000 {
+        tree.insert((-3., 5., 7.)).unwrap();
+    }
+    assert_eq!(tree.depth(), 5);
+    assert_eq!(tree.query_sphere((-3., 5., 7.), 0.).len(), 1000);
+
+    tree.rebuild().unwrap();
+    assert_eq!(tree.depth(), 5);
+    assert_eq!(tree.k_nearest((0., 0., 0.), 10).len(), 10);
+}
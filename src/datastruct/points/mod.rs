@@ -1,3 +1,5 @@
+use std::ops::{Add, Div, Mul, Neg, Sub};
+
 use my_rust_matrix_lib::my_matrix_lib::prelude::VectorMath;
 use num::Float;
 
@@ -5,6 +7,7 @@ use num::Float;
 mod test;
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point<F: Float + Copy> {
     pub x: F,
     pub y: F,
@@ -36,6 +39,182 @@ impl<F: Float + Copy> Point<F> {
         let dy = (other.y - self.y).abs();
         dx.max(dy)
     }
+
+    #[inline(always)]
+    pub fn manhattan_dist(self, other: Self) -> F {
+        (other.x - self.x).abs() + (other.y - self.y).abs()
+    }
+
+    #[inline(always)]
+    pub fn dot(self, other: Self) -> F {
+        self.x * other.x + self.y * other.y
+    }
+
+    ///z coordinate of the 3d cross product, positive if `other` is counterclockwise from `self`
+    #[inline(always)]
+    pub fn cross(self, other: Self) -> F {
+        self.x * other.y - self.y * other.x
+    }
+
+    ///`self` for `t = 0`, `other` for `t = 1`
+    #[inline(always)]
+    pub fn lerp(self, other: Self, t: F) -> Self {
+        self * (F::one() - t) + other * t
+    }
+
+    #[inline(always)]
+    pub fn length_sq(self) -> F {
+        self.dot(self)
+    }
+
+    #[inline(always)]
+    pub fn length(self) -> F {
+        self.length_sq().sqrt()
+    }
+
+    ///Same direction with a length of 1, the zero vector stays zero.
+    #[inline(always)]
+    pub fn normalize(self) -> Self {
+        let length = self.length();
+        if length == F::zero() {
+            self
+        } else {
+            self / length
+        }
+    }
+
+    ///Counterclockwise rotation around the origin, `angle` in radians
+    #[inline(always)]
+    pub fn rotate(self, angle: F) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self {
+            x: self.x * cos - self.y * sin,
+            y: self.x * sin + self.y * cos,
+        }
+    }
+
+    ///Counterclockwise rotation by a quarter turn
+    #[inline(always)]
+    pub fn perp(self) -> Self {
+        Self {
+            x: -self.y,
+            y: self.x,
+        }
+    }
+}
+
+impl<F: Float + Copy> Add for Point<F> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+        }
+    }
+}
+
+impl<F: Float + Copy> Sub for Point<F> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
+
+impl<F: Float + Copy> Mul<F> for Point<F> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: F) -> Self {
+        Self {
+            x: self.x * rhs,
+            y: self.y * rhs,
+        }
+    }
+}
+
+impl<F: Float + Copy> Div<F> for Point<F> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn div(self, rhs: F) -> Self {
+        Self {
+            x: self.x / rhs,
+            y: self.y / rhs,
+        }
+    }
+}
+
+impl<F: Float + Copy> Neg for Point<F> {
+    type Output = Self;
+
+    #[inline(always)]
+    fn neg(self) -> Self {
+        Self {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+}
+
+impl<F: Float + Copy> From<(F, F)> for Point<F> {
+    #[inline(always)]
+    fn from(value: (F, F)) -> Self {
+        value.as_point()
+    }
+}
+
+impl<F: Float + Copy> From<[F; 2]> for Point<F> {
+    #[inline(always)]
+    fn from(value: [F; 2]) -> Self {
+        value.as_point()
+    }
+}
+
+impl<F: Float + Copy> From<VectorMath<F, 2>> for Point<F> {
+    #[inline(always)]
+    fn from(value: VectorMath<F, 2>) -> Self {
+        value.as_point()
+    }
+}
+
+impl<F: Float + Copy> From<Point<F>> for (F, F) {
+    #[inline(always)]
+    fn from(value: Point<F>) -> Self {
+        (value.x, value.y)
+    }
+}
+
+impl<F: Float + Copy> From<Point<F>> for [F; 2] {
+    #[inline(always)]
+    fn from(value: Point<F>) -> Self {
+        [value.x, value.y]
+    }
+}
+
+impl<F: Float + Copy> From<Point<F>> for VectorMath<F, 2> {
+    #[inline(always)]
+    fn from(value: Point<F>) -> Self {
+        VectorMath::from([value.x, value.y])
+    }
+}
+
+impl<F: Float + Copy> As2dPoint<F> for Point<F> {
+    #[inline(always)]
+    fn x(&self) -> F {
+        self.x
+    }
+
+    #[inline(always)]
+    fn y(&self) -> F {
+        self.y
+    }
 }
 
 impl<F: Float + Copy> As2dPoint<F> for (F, F) {
@@ -87,6 +266,7 @@ impl<F: Float + Copy> As2dPoint<F> for IndexPoint<F> {
 }
 
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct IndexPoint<F: Float + Copy> {
     pub x: F,
     pub y: F,
@@ -120,3 +300,158 @@ pub trait As2dPoint<F: Float + Copy> {
         }
     }
 }
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point3<F: Float + Copy> {
+    pub x: F,
+    pub y: F,
+    pub z: F,
+}
+
+impl<F: Float + Copy> Point3<F> {
+    ///false if any coordinate is NaN or Infinite
+    #[inline(always)]
+    pub fn as_valid_coord(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite() && self.z.is_finite()
+    }
+
+    #[inline(always)]
+    pub fn dist_sq(self, other: Self) -> F {
+        let dx = other.x - self.x;
+        let dy = other.y - self.y;
+        let dz = other.z - self.z;
+
+        dx * dx + dy * dy + dz * dz
+    }
+
+    #[inline(always)]
+    pub fn dist(self, other: Self) -> F {
+        self.dist_sq(other).sqrt()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IndexPoint3<F: Float + Copy> {
+    pub x: F,
+    pub y: F,
+    pub z: F,
+    pub i: usize,
+}
+
+impl<F: Float + Copy> IndexPoint3<F> {
+    #[inline(always)]
+    pub fn new(x: F, y: F, z: F, i: usize) -> Self {
+        Self { x, y, z, i }
+    }
+
+    #[inline(always)]
+    pub fn into_point(self) -> Point3<F> {
+        Point3 {
+            x: self.x,
+            y: self.y,
+            z: self.z,
+        }
+    }
+}
+
+pub trait As3dPoint<F: Float + Copy> {
+    fn x(&self) -> F;
+    fn y(&self) -> F;
+    fn z(&self) -> F;
+
+    #[inline(always)]
+    fn as_point3(&self) -> Point3<F> {
+        Point3 {
+            x: self.x(),
+            y: self.y(),
+            z: self.z(),
+        }
+    }
+}
+
+impl<F: Float + Copy> As3dPoint<F> for (F, F, F) {
+    #[inline(always)]
+    fn x(&self) -> F {
+        self.0
+    }
+
+    #[inline(always)]
+    fn y(&self) -> F {
+        self.1
+    }
+
+    #[inline(always)]
+    fn z(&self) -> F {
+        self.2
+    }
+}
+
+impl<F: Float + Copy> As3dPoint<F> for [F; 3] {
+    #[inline(always)]
+    fn x(&self) -> F {
+        self[0]
+    }
+
+    #[inline(always)]
+    fn y(&self) -> F {
+        self[1]
+    }
+
+    #[inline(always)]
+    fn z(&self) -> F {
+        self[2]
+    }
+}
+
+impl<F: Float + Copy> As3dPoint<F> for VectorMath<F, 3> {
+    #[inline(always)]
+    fn x(&self) -> F {
+        self[0]
+    }
+
+    #[inline(always)]
+    fn y(&self) -> F {
+        self[1]
+    }
+
+    #[inline(always)]
+    fn z(&self) -> F {
+        self[2]
+    }
+}
+
+impl<F: Float + Copy> As3dPoint<F> for Point3<F> {
+    #[inline(always)]
+    fn x(&self) -> F {
+        self.x
+    }
+
+    #[inline(always)]
+    fn y(&self) -> F {
+        self.y
+    }
+
+    #[inline(always)]
+    fn z(&self) -> F {
+        self.z
+    }
+}
+
+impl<F: Float + Copy> As3dPoint<F> for IndexPoint3<F> {
+    #[inline(always)]
+    fn x(&self) -> F {
+        self.x
+    }
+
+    #[inline(always)]
+    fn y(&self) -> F {
+        self.y
+    }
+
+    #[inline(always)]
+    fn z(&self) -> F {
+        self.z
+    }
+}
@@ -1 +1,147 @@
 #![cfg(test)]
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use my_rust_matrix_lib::my_matrix_lib::prelude::VectorMath;
+
+use crate::datastruct::{
+    points::{As2dPoint, As3dPoint, IndexPoint3, Point, Point3},
+    test_util::Rng,
+};
+
+///random points with coordinates in `[-100, 100)`
+fn random_points(nb: usize, seed: u64) -> Vec<Point<f64>> {
+    let mut rng = Rng::new(seed);
+    (0..nb)
+        .map(|_| Point {
+            x: rng.range(-100., 100.),
+            y: rng.range(-100., 100.),
+        })
+        .collect()
+}
+
+fn assert_close(a: Point<f64>, b: Point<f64>) {
+    assert!(a.dist(b) < 1e-9, "{a:?} != {b:?}");
+}
+
+#[test]
+fn test_point_arithmetic_properties() {
+    let points = random_points(600, 0xc0ffee);
+    for w in points.windows(3) {
+        let (a, b, c) = (w[0], w[1], w[2]);
+        let s = c.x;
+
+        assert_close(a + b - b, a);
+        assert_close(a + b, b + a);
+        assert_close((a + b) + c, a + (b + c));
+        assert_close(a - b, a + -b);
+        assert_close(-(-a), a);
+        assert_close(a * s / s, a);
+        assert_close((a + b) * s, a * s + b * s);
+
+        assert_eq!(a.dot(b), b.dot(a));
+        assert_eq!(a.cross(b), -b.cross(a));
+        assert_eq!(a.cross(a), 0.);
+        assert_eq!(a.dot(a.perp()), 0.);
+        assert!((a.cross(a.perp()) - a.length_sq()).abs() < 1e-9 * a.length_sq());
+        assert!((a.dot(b + c) - a.dot(b) - a.dot(c)).abs() < 1e-9);
+        assert!((a.length() * a.length() - a.dot(a)).abs() < 1e-9);
+    }
+}
+
+#[test]
+fn test_point_geometry_properties() {
+    let points = random_points(600, 0xbad_cafe);
+    for w in points.windows(3) {
+        let (a, b, c) = (w[0], w[1], w[2]);
+        let angle = c.x / 100. * PI;
+
+        //the ends are exact, the middle is halfway
+        let (t0, t1) = (a.lerp(b, 0.), a.lerp(b, 1.));
+        assert_eq!((t0.x, t0.y, t1.x, t1.y), (a.x, a.y, b.x, b.y));
+        let mid = a.lerp(b, 0.5);
+        assert!((mid.dist(a) - mid.dist(b)).abs() < 1e-9);
+
+        assert!((a.normalize().length() - 1.).abs() < 1e-12);
+        assert!(a.normalize().cross(a).abs() < 1e-9);
+        assert!(a.normalize().dot(a) > 0.);
+
+        assert!((a.rotate(angle).length() - a.length()).abs() < 1e-9);
+        assert_close(a.rotate(angle).rotate(-angle), a);
+        assert_close(a.rotate(FRAC_PI_2), a.perp());
+        assert!((a.rotate(angle).dot(b.rotate(angle)) - a.dot(b)).abs() < 1e-9);
+
+        assert!(a.tchebychev_dist(b) <= a.dist(b));
+        assert!(a.dist(b) <= a.manhattan_dist(b));
+        assert!(a.manhattan_dist(c) <= a.manhattan_dist(b) + b.manhattan_dist(c));
+        assert_eq!(a.manhattan_dist(b), b.manhattan_dist(a));
+        assert!((a.dist(b) - (b - a).length()).abs() < 1e-12);
+    }
+
+    let zero = Point { x: 0f32, y: 0. };
+    let normalized = zero.normalize();
+    assert_eq!((normalized.x, normalized.y), (0., 0.));
+}
+
+#[test]
+fn test_point_conversions() {
+    for p in random_points(100, 3) {
+        let tuple: (f64, f64) = p.into();
+        let array: [f64; 2] = p.into();
+        let vector: VectorMath<f64, 2> = p.into();
+        assert_eq!(tuple, (p.x, p.y));
+        assert_eq!(array, [p.x, p.y]);
+        assert_eq!((vector[0], vector[1]), (p.x, p.y));
+
+        for back in [
+            Point::from(tuple),
+            Point::from(array),
+            Point::from(vector),
+            p.as_point(),
+        ] {
+            assert_eq!((back.x, back.y), (p.x, p.y));
+        }
+    }
+}
+
+#[test]
+fn test_as_3d_point() {
+    let expected = (1., -2., 3.5);
+    let tuple = (1f32, -2f32, 3.5f32);
+    let array = [1f32, -2., 3.5];
+    let i_p = IndexPoint3::new(1f32, -2., 3.5, 4);
+
+    for p in [tuple.as_point3(), array.as_point3(), i_p.as_point3()] {
+        assert_eq!((p.x, p.y, p.z), expected);
+    }
+    assert_eq!(
+        Point3 {
+            x: 0.,
+            y: 0.,
+            z: 0.
+        }
+        .dist_sq(Point3 {
+            x: 1.,
+            y: 2.,
+            z: 2.
+        }),
+        9.
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_points_serde() {
+    use crate::datastruct::points::{IndexPoint, Point};
+
+    let point = Point { x: 1.25f32, y: -3. };
+    let json = serde_json::to_string(&point).unwrap();
+    assert_eq!(json, r#"{"x":1.25,"y":-3.0}"#);
+    let back: Point<f32> = serde_json::from_str(&json).unwrap();
+    assert_eq!((back.x, back.y), (1.25, -3.));
+
+    let i_p = IndexPoint::new(0.5f64, 2., 7);
+    let back: IndexPoint<f64> =
+        serde_json::from_str(&serde_json::to_string(&i_p).unwrap()).unwrap();
+    assert_eq!((back.x, back.y, back.i), (0.5, 2., 7));
+}
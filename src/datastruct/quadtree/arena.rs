@@ -0,0 +1,514 @@
+use std::{collections::BinaryHeap, fmt::Debug};
+
+use arrayvec::ArrayVec;
+use num::Float;
+
+use super::{DEFAULT_MAX_DEPTH, NodeLeafData, QuadtreeError, fitting_boundary, morton_index};
+use crate::datastruct::{
+    aabb::Aabb,
+    nearest::{NearestEntry, NearestItem},
+    points::{As2dPoint, IndexPoint, Point},
+    shapes::{Circle, Shape2d},
+    slotmap::{Handle, SlotMap},
+    util::partition,
+};
+
+const ROOT: usize = 0;
+
+///Point quadtree with `N` elements per leaf, like [`Quadtree`](super::Quadtree),
+///storing its nodes in a single `Vec` instead of boxing them.
+///
+///The four children of a node are next to each other, in Morton order (down left, down right,
+///up left, up right). `new` and `rebuild` lay the whole tree out depth first in this order, so
+///nodes close in space are close in memory. Insertions append the new children at the end of
+///the arena and removals recycle them. The nodes do not keep summaries.
+#[derive(Debug, Clone)]
+pub struct ArenaQuadtree<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize> {
+    elems: SlotMap<T>,
+    ///position of each element in the tree, by slot index
+    positions: Vec<Point<F>>,
+    nodes: Vec<ArenaNode<F, N>>,
+    ///first nodes of the blocks of 4 children left by merges
+    free_blocks: Vec<usize>,
+    max_depth: usize,
+}
+
+#[derive(Debug, Clone)]
+struct ArenaNode<F: Float + Copy + Debug, const N: usize> {
+    boundary: Aabb<F>,
+    data: ArenaNodeData<F, N>,
+}
+
+#[derive(Debug, Clone)]
+enum ArenaNodeData<F: Float + Copy + Debug, const N: usize> {
+    ///index of the first of the 4 children
+    Child(usize),
+    Leaf(NodeLeafData<F, N>),
+}
+
+#[allow(dead_code)]
+impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize> ArenaQuadtree<F, T, N> {
+    pub fn empty(boundary: Aabb<F>) -> Self {
+        debug_assert!(N > 0, "The size should be a least 1");
+
+        Self {
+            elems: SlotMap::new(),
+            positions: vec![],
+            nodes: vec![ArenaNode::leaf(boundary)],
+            free_blocks: vec![],
+            max_depth: DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    ///The element `i` of `vec` can be found using the `i`-th handle of `iter_with_handles`.
+    ///`boundary` grows to contain all the elements.
+    pub fn new(boundary: Aabb<F>, vec: Vec<T>) -> Self {
+        debug_assert!(N > 0, "The size should be a least 1");
+
+        let elems: SlotMap<T> = vec.into();
+        let mut result = Self {
+            nodes: vec![ArenaNode::leaf(fitting_boundary(boundary, &elems))],
+            elems,
+            positions: vec![],
+            free_blocks: vec![],
+            max_depth: DEFAULT_MAX_DEPTH,
+        };
+        result
+            .rebuild()
+            .expect("something went wrong in ArenaQuadtree::new: the boundary should fit");
+        result
+    }
+
+    ///Maximum depth of the tree, the root being at depth 1. Panics if `max_depth` is 0.
+    ///Applies to the next insertions, call `rebuild` to apply it to the whole tree.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.set_max_depth(max_depth);
+        self
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        assert!(max_depth > 0, "The max depth should be a least 1");
+        self.max_depth = max_depth;
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.elems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth_at(ROOT)
+    }
+
+    pub fn boundary(&self) -> Aabb<F> {
+        self.nodes[ROOT].boundary
+    }
+
+    ///Number of nodes in use in the arena.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - 4 * self.free_blocks.len()
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.elems.get(handle)
+    }
+
+    ///After moving the element, call `update_position` to move it in the tree.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.elems.get_mut(handle)
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.elems.contains(handle)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.elems.values()
+    }
+
+    pub fn iter_with_handles(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.elems.iter()
+    }
+
+    pub fn insert(&mut self, elem: T) -> Result<Handle, QuadtreeError<F>> {
+        let i_p = IndexPoint::new(elem.x(), elem.y(), usize::MAX);
+
+        if !i_p.into_point().as_valid_coord() {
+            return Err(QuadtreeError::InvalidCoord((i_p.x, i_p.y)));
+        }
+        if !self.boundary().contain_pt(i_p.into_point()) {
+            return Err(QuadtreeError::OutOfBoundary(
+                self.boundary(),
+                (i_p.x, i_p.y),
+            ));
+        }
+
+        let handle = self.elems.insert(elem);
+        self.set_position(handle.index(), i_p.into_point());
+        self.insert_at(IndexPoint {
+            i: handle.index(),
+            ..i_p
+        })
+        .expect("something went wrong in ArenaQuadtree::insert: the point is in the boundary");
+        Ok(handle)
+    }
+
+    ///Removes the element of `handle` and returns it, `None` if the handle is no longer valid.
+    ///Other handles stay valid.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let elem = self.elems.remove(handle)?;
+
+        let removed = self.remove_at(ROOT, self.stored_point(handle.index()));
+        debug_assert!(
+            removed,
+            "something went wrong in ArenaQuadtree::remove: the element is not in the tree"
+        );
+        Some(elem)
+    }
+
+    ///Moves the element of `handle` in the tree to its current position,
+    ///to call after modifying it through `get_mut`.
+    ///On error, the element keeps its previous place in the tree.
+    pub fn update_position(&mut self, handle: Handle) -> Result<(), QuadtreeError<F>> {
+        let elem = self
+            .elems
+            .get(handle)
+            .ok_or(QuadtreeError::InvalidHandle(handle))?;
+        let new_i_p = IndexPoint::new(elem.x(), elem.y(), handle.index());
+
+        if !new_i_p.into_point().as_valid_coord() {
+            return Err(QuadtreeError::InvalidCoord((new_i_p.x, new_i_p.y)));
+        }
+        if !self.boundary().contain_pt(new_i_p.into_point()) {
+            return Err(QuadtreeError::OutOfBoundary(
+                self.boundary(),
+                (new_i_p.x, new_i_p.y),
+            ));
+        }
+
+        let removed = self.remove_at(ROOT, self.stored_point(handle.index()));
+        debug_assert!(
+            removed,
+            "something went wrong in ArenaQuadtree::update_position: the element is not in the tree"
+        );
+        self.set_position(handle.index(), new_i_p.into_point());
+        self.insert_at(new_i_p)
+    }
+
+    ///Rebuilds the tree from its elements, laying the nodes out depth first in Morton order.
+    pub fn rebuild(&mut self) -> Result<(), QuadtreeError<F>> {
+        let mut points = Vec::with_capacity(self.elems.len());
+        for (handle, elem) in self.elems.iter() {
+            let i_p = IndexPoint::new(elem.x(), elem.y(), handle.index());
+            if !i_p.into_point().as_valid_coord() {
+                return Err(QuadtreeError::InvalidCoord((i_p.x, i_p.y)));
+            }
+            if !self.boundary().contain_pt(i_p.into_point()) {
+                return Err(QuadtreeError::OutOfBoundary(
+                    self.boundary(),
+                    (i_p.x, i_p.y),
+                ));
+            }
+            points.push(i_p);
+        }
+        for i_p in &points {
+            self.set_position(i_p.i, i_p.into_point());
+        }
+
+        let boundary = self.boundary();
+        self.nodes.clear();
+        self.free_blocks.clear();
+        self.nodes.push(ArenaNode::leaf(boundary));
+        self.build(ROOT, &mut points, 1);
+        Ok(())
+    }
+
+    pub fn query_range(&self, range: Aabb<F>) -> Vec<&T> {
+        let mut result = vec![];
+        let mut stack = vec![ROOT];
+
+        while let Some(curr) = stack.pop() {
+            let node = &self.nodes[curr];
+            if !node.boundary.intersect(range) {
+                continue;
+            }
+            match &node.data {
+                ArenaNodeData::Child(first) => stack.extend(*first..*first + 4),
+                ArenaNodeData::Leaf(leaf) => result.extend(
+                    leaf.iter()
+                        .filter(|i_p| range.contain_pt(i_p.into_point()))
+                        .map(|i_p| self.elem_at(i_p.i)),
+                ),
+            }
+        }
+        result
+    }
+
+    ///Elements at most `radius` away from `center`.
+    pub fn query_circle<P: As2dPoint<F>>(&self, center: P, radius: F) -> Vec<&T> {
+        self.query_shape(&Circle::new(center, radius))
+    }
+
+    ///Elements inside `shape`.
+    pub fn query_shape<Sh: Shape2d<F>>(&self, shape: &Sh) -> Vec<&T> {
+        let mut result = vec![];
+        let mut stack = vec![ROOT];
+
+        while let Some(curr) = stack.pop() {
+            let node = &self.nodes[curr];
+            if !shape.intersects_aabb(node.boundary) {
+                continue;
+            }
+            match &node.data {
+                ArenaNodeData::Child(first) => stack.extend(*first..*first + 4),
+                ArenaNodeData::Leaf(leaf) => result.extend(
+                    leaf.iter()
+                        .filter(|i_p| shape.contains_point(i_p.into_point()))
+                        .map(|i_p| self.elem_at(i_p.i)),
+                ),
+            }
+        }
+        result
+    }
+
+    ///Closest element to `point` with its distance, `None` if the tree is empty.
+    pub fn nearest<P: As2dPoint<F>>(&self, point: P) -> Option<(&T, F)> {
+        self.k_nearest(point, 1).into_iter().next()
+    }
+
+    ///The `k` closest elements to `point` with their distances, sorted by distance.
+    pub fn k_nearest<P: As2dPoint<F>>(&self, point: P, k: usize) -> Vec<(&T, F)> {
+        let point = point.as_point();
+        let mut result = Vec::with_capacity(k);
+        if k == 0 {
+            return result;
+        }
+
+        let mut heap = BinaryHeap::new();
+        heap.push(NearestEntry {
+            key: self.nodes[ROOT].boundary.dist_sq_to_pt(point),
+            item: NearestItem::Node(ROOT),
+        });
+
+        while let Some(NearestEntry { key: dist_sq, item }) = heap.pop() {
+            match item {
+                NearestItem::Elem(i) => {
+                    result.push((self.elem_at(i), dist_sq.sqrt()));
+                    if result.len() == k {
+                        break;
+                    }
+                }
+                NearestItem::Node(curr) => match &self.nodes[curr].data {
+                    ArenaNodeData::Child(first) => {
+                        for child in *first..*first + 4 {
+                            heap.push(NearestEntry {
+                                key: self.nodes[child].boundary.dist_sq_to_pt(point),
+                                item: NearestItem::Node(child),
+                            });
+                        }
+                    }
+                    ArenaNodeData::Leaf(leaf) => {
+                        for i_p in leaf.iter() {
+                            heap.push(NearestEntry {
+                                key: i_p.into_point().dist_sq(point),
+                                item: NearestItem::Elem(i_p.i),
+                            });
+                        }
+                    }
+                },
+            }
+        }
+        result
+    }
+
+    fn insert_at(&mut self, p_i: IndexPoint<F>) -> Result<(), QuadtreeError<F>> {
+        let pt = p_i.into_point();
+        let mut curr = ROOT;
+        let mut depth = 1;
+
+        loop {
+            let boundary = self.nodes[curr].boundary;
+            if !boundary.contain_pt(pt) {
+                return Err(QuadtreeError::OutOfBoundary(boundary, (p_i.x, p_i.y)));
+            }
+            match &mut self.nodes[curr].data {
+                ArenaNodeData::Child(first) => {
+                    curr = *first + morton_index(boundary.diag_pos_from_center(pt));
+                    depth += 1;
+                }
+                ArenaNodeData::Leaf(leaf) => {
+                    if !leaf.points.is_full() {
+                        leaf.points.push(p_i);
+                        return Ok(());
+                    } else if depth >= self.max_depth || !leaf.overflow.is_empty() {
+                        leaf.overflow.push(p_i);
+                        return Ok(());
+                    } else {
+                        self.subdivide(curr);
+                    }
+                }
+            }
+        }
+    }
+
+    ///turns the leaf `curr` into a node with 4 leaf children, in a recycled block if possible
+    fn subdivide(&mut self, curr: usize) {
+        let boundary = self.nodes[curr].boundary;
+        let ArenaNodeData::Leaf(leaf) = &mut self.nodes[curr].data else {
+            return;
+        };
+        let leaf = std::mem::take(leaf);
+
+        let first = self.alloc_block(boundary);
+        for i_p in leaf.iter() {
+            let child = first + morton_index(boundary.diag_pos_from_center(i_p.into_point()));
+            if let ArenaNodeData::Leaf(child_leaf) = &mut self.nodes[child].data {
+                child_leaf.points.push(*i_p);
+            }
+        }
+        self.nodes[curr].data = ArenaNodeData::Child(first);
+    }
+
+    ///4 empty leaves splitting `boundary`, returns the index of the first one
+    fn alloc_block(&mut self, boundary: Aabb<F>) -> usize {
+        let [ul, ur, dr, dl] = boundary.subdivide();
+        let block = [dl, dr, ul, ur].map(ArenaNode::leaf);
+
+        match self.free_blocks.pop() {
+            Some(first) => {
+                for (node, new) in self.nodes[first..first + 4].iter_mut().zip(block) {
+                    *node = new;
+                }
+                first
+            }
+            None => {
+                let first = self.nodes.len();
+                self.nodes.extend(block);
+                first
+            }
+        }
+    }
+
+    ///lays out the subtree of `curr` holding `points`, its children blocks after it.
+    ///`points` is reordered in place, in Morton order.
+    fn build(&mut self, curr: usize, points: &mut [IndexPoint<F>], depth: usize) {
+        if points.len() <= N || depth >= self.max_depth {
+            let mut leaf = NodeLeafData::default();
+            let split = points.len().min(N);
+            leaf.points.extend(points[..split].iter().copied());
+            leaf.overflow.extend_from_slice(&points[split..]);
+            self.nodes[curr].data = ArenaNodeData::Leaf(leaf);
+            return;
+        }
+
+        let boundary = self.nodes[curr].boundary;
+        let first = self.alloc_block(boundary);
+        self.nodes[curr].data = ArenaNodeData::Child(first);
+
+        //down then up, and left then right in each half, like `morton_index`
+        let is_up = |i_p: &IndexPoint<F>| i_p.y > boundary.center.y;
+        let is_right = |i_p: &IndexPoint<F>| i_p.x > boundary.center.x;
+        let mid = partition(points, |i_p| !is_up(i_p));
+        let (down, up) = points.split_at_mut(mid);
+        let down_mid = partition(down, |i_p| !is_right(i_p));
+        let up_mid = partition(up, |i_p| !is_right(i_p));
+        let (dl, dr) = down.split_at_mut(down_mid);
+        let (ul, ur) = up.split_at_mut(up_mid);
+
+        for (k, part) in [dl, dr, ul, ur].into_iter().enumerate() {
+            self.build(first + k, part, depth + 1);
+        }
+    }
+
+    ///the point of index `i` as it is stored in the tree, whatever the element's current position
+    #[inline(always)]
+    fn stored_point(&self, i: usize) -> IndexPoint<F> {
+        let pos = self.positions[i];
+        IndexPoint::new(pos.x, pos.y, i)
+    }
+
+    #[inline(always)]
+    fn set_position(&mut self, i: usize, pos: Point<F>) {
+        if i >= self.positions.len() {
+            self.positions.resize(i + 1, pos);
+        }
+        self.positions[i] = pos;
+    }
+
+    ///removes the point `i_p`, descending by its stored coordinates and merging the nodes left under capacity
+    fn remove_at(&mut self, curr: usize, i_p: IndexPoint<F>) -> bool {
+        let boundary = self.nodes[curr].boundary;
+        match &mut self.nodes[curr].data {
+            ArenaNodeData::Leaf(leaf) => leaf.remove(i_p.i),
+            ArenaNodeData::Child(first) => {
+                let first = *first;
+                let child = first + morton_index(boundary.diag_pos_from_center(i_p.into_point()));
+                let removed = self.remove_at(child, i_p);
+                if removed {
+                    self.try_merge(curr, first);
+                }
+                removed
+            }
+        }
+    }
+
+    ///turns `curr` back into a leaf if its children are leaves holding at most N points
+    fn try_merge(&mut self, curr: usize, first: usize) {
+        let mut points = ArrayVec::new();
+        for node in &self.nodes[first..first + 4] {
+            match &node.data {
+                ArenaNodeData::Leaf(leaf) => {
+                    if !leaf.overflow.is_empty()
+                        || points.try_extend_from_slice(&leaf.points).is_err()
+                    {
+                        return;
+                    }
+                }
+                ArenaNodeData::Child(_) => return,
+            }
+        }
+
+        self.nodes[curr].data = ArenaNodeData::Leaf(NodeLeafData {
+            points,
+            overflow: vec![],
+        });
+        for node in &mut self.nodes[first..first + 4] {
+            node.data = ArenaNodeData::Leaf(NodeLeafData::default());
+        }
+        self.free_blocks.push(first);
+    }
+
+    fn depth_at(&self, curr: usize) -> usize {
+        1 + match &self.nodes[curr].data {
+            ArenaNodeData::Child(first) => (*first..*first + 4)
+                .map(|child| self.depth_at(child))
+                .max()
+                .unwrap_or(0),
+            ArenaNodeData::Leaf(_) => 0,
+        }
+    }
+
+    ///element of a slot referenced by the tree
+    #[inline(always)]
+    fn elem_at(&self, i: usize) -> &T {
+        self.elems
+            .get_by_index(i)
+            .expect("something went wrong in ArenaQuadtree: the tree refers to an empty slot")
+    }
+}
+
+impl<F: Float + Copy + Debug, const N: usize> ArenaNode<F, N> {
+    fn leaf(boundary: Aabb<F>) -> Self {
+        Self {
+            boundary,
+            data: ArenaNodeData::Leaf(NodeLeafData::default()),
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::{Debug, Write};
+
+use glium::{VertexBuffer, backend::Facade, vertex::BufferCreationError};
+use num::Float;
+
+use super::{Aabb, As2dPoint, Node, NodeData, Quadtree, Summary};
+use crate::mesh::vertex::Vertex;
+
+impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize, S: Summary<T>> Quadtree<F, T, N, S> {
+    ///Boundaries of all the nodes with their depth (the root being at depth 1), parents first.
+    pub fn node_boundaries(&self) -> Vec<(Aabb<F>, usize)> {
+        let mut result = vec![];
+        let mut stack: Vec<(&Node<F, N, S>, usize)> = vec![(&self.base_node, 1)];
+
+        while let Some((curr_node, depth)) = stack.pop() {
+            result.push((curr_node.boundary, depth));
+            if let NodeData::Child(child) = &curr_node.data {
+                stack.extend(
+                    child
+                        .children()
+                        .into_iter()
+                        .rev()
+                        .map(|node| (node, depth + 1)),
+                );
+            }
+        }
+        result
+    }
+
+    ///Outlines of the nodes as a line list, 4 segments (8 vertices) per node.
+    pub fn debug_line_vertices(&self) -> Vec<Vertex> {
+        let to_f32 = |x: F| x.to_f32().unwrap_or(f32::NAN);
+
+        self.node_boundaries()
+            .into_iter()
+            .flat_map(|(aabb, _)| {
+                let (min, max) = (aabb.min(), aabb.max());
+                let corners = [
+                    [to_f32(min.x), to_f32(min.y)],
+                    [to_f32(max.x), to_f32(min.y)],
+                    [to_f32(max.x), to_f32(max.y)],
+                    [to_f32(min.x), to_f32(max.y)],
+                ];
+                (0..4).flat_map(move |k| [corners[k], corners[(k + 1) % 4]].map(Vertex::from))
+            })
+            .collect()
+    }
+
+    ///Outlines of the nodes in a vertex buffer,
+    ///to draw with `glium::index::NoIndices(PrimitiveType::LinesList)`.
+    pub fn debug_vertex_buffer<Fa: Facade + ?Sized>(
+        &self,
+        facade: &Fa,
+    ) -> Result<VertexBuffer<Vertex>, BufferCreationError> {
+        VertexBuffer::new(facade, &self.debug_line_vertices())
+    }
+
+    ///SVG image of the nodes, coloured by depth, and of the elements.
+    ///The y axis points up like in the tree.
+    pub fn to_svg(&self) -> String {
+        let to_f64 = |x: F| x.to_f64().unwrap_or(f64::NAN);
+        let boundary = self.base_node.boundary;
+        let (min, max) = (boundary.min(), boundary.max());
+        let dot_radius = to_f64(boundary.width().max(boundary.height())) / 400.;
+
+        let mut svg = String::new();
+        //writing to a String does not fail
+        let _ = writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+            to_f64(min.x),
+            -to_f64(max.y),
+            to_f64(boundary.width()),
+            to_f64(boundary.height()),
+        );
+        let _ = writeln!(
+            svg,
+            r#"<g fill="none" stroke-width="1" vector-effect="non-scaling-stroke">"#
+        );
+        for (aabb, depth) in self.node_boundaries() {
+            let (min, max) = (aabb.min(), aabb.max());
+            let _ = writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" stroke="{}" vector-effect="non-scaling-stroke"/>"#,
+                to_f64(min.x),
+                -to_f64(max.y),
+                to_f64(aabb.width()),
+                to_f64(aabb.height()),
+                depth_color(depth),
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+
+        let _ = writeln!(svg, r#"<g fill="black">"#);
+        for elem in self.elems.values() {
+            let _ = writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}"/>"#,
+                to_f64(elem.x()),
+                -to_f64(elem.y()),
+                dot_radius,
+            );
+        }
+        let _ = writeln!(svg, "</g>");
+        let _ = writeln!(svg, "</svg>");
+        svg
+    }
+}
+
+///a hue per depth, the root in red
+fn depth_color(depth: usize) -> String {
+    format!("hsl({}, 80%, 45%)", ((depth - 1) * 47) % 360)
+}
@@ -1,65 +1,138 @@
-use std::{fmt::Debug, mem::MaybeUninit};
+use std::{cmp::Ordering, collections::BinaryHeap, fmt::Debug};
 
 use arrayvec::ArrayVec;
 use num::Float;
 
 use super::{
     aabb::{Aabb, DiagonalDirection},
-    points::{As2dPoint, IndexPoint},
+    error::SpatialError2d,
+    leaf::{LeafData, LeafIter},
+    nearest::{NearestEntry, NearestItem},
+    points::{As2dPoint, IndexPoint, Point},
+    shapes::{Circle, Shape2d},
+    slotmap::{Handle, SlotMap},
+    util::grown_bounds,
 };
 
+mod arena;
+mod debug;
+#[cfg(feature = "parallel")]
+mod parallel;
+#[cfg(feature = "serde")]
+mod serialize;
+mod stats;
+mod summary;
 #[cfg(test)]
 mod test;
 
+pub use arena::*;
+pub use stats::*;
+pub use summary::*;
+
+///Point quadtree with `N` elements per leaf.
+///
+///Leaves at `max_depth` are not subdivided, they keep the elements past `N` in an overflow bucket,
+///so coincident points do not make the tree grow indefinitely.
+///
+///Each node keeps a summary `S` of its elements (see [`Summary`]), updated on insertion, removal
+///and rebuild. Summaries are not updated when elements are modified in place, see `refresh_summaries`.
 #[derive(Debug, Clone)]
-pub struct Quadtree<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize> {
-    vec: Vec<T>,
-    base_node: Node<F, N>,
+pub struct Quadtree<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize, S: Summary<T> = ()> {
+    elems: SlotMap<T>,
+    base_node: Node<F, N, S>,
+    query_mode: QueryMode,
+    max_depth: usize,
 }
 
-#[derive(Debug, Clone, Copy)]
-pub enum QuadtreeError<F: Float + Copy + Debug> {
-    OutOfBoundary(Aabb<F>, (F, F)),
-    InvalidCoord((F, F)),
+///Default `max_depth` of a [`Quadtree`].
+pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+///How the range queries (`query_range` and the `map_*` functions) select the elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QueryMode {
+    ///Only the elements inside the range.
+    #[default]
+    Exact,
+    ///Every element of the leaves intersecting the range, may contain elements out of the range.
+    ///Faster, useful as a broad phase before a finer test.
+    BroadPhase,
 }
 
-impl<F: Float + Copy + Debug + Debug> std::fmt::Display for QuadtreeError<F> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            QuadtreeError::OutOfBoundary(aabb, pt) => {
-                write!(f, "{:?} does not contains the point {:?}.", aabb, pt)
-            }
-            QuadtreeError::InvalidCoord(coord) => {
-                write!(f, "point of coord {:?} are invalid.", { coord })
-            }
-        }
-    }
-}
+pub type QuadtreeError<F> = SpatialError2d<F>;
 
 #[allow(dead_code)]
-impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize> Quadtree<F, T, N> {
+impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize, S: Summary<T>> Quadtree<F, T, N, S> {
     pub fn empty(boundary: Aabb<F>) -> Self {
         debug_assert!(N > 0, "The size should be a least 1");
 
         Self {
-            vec: vec![],
+            elems: SlotMap::new(),
             base_node: Node::empty(boundary),
+            query_mode: QueryMode::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
         }
     }
 
+    ///The element `i` of `vec` can be found using the `i`-th handle of `iter_with_handles`.
+    ///`boundary` grows to contain all the elements. The tree is bulk loaded, see `from_slice`.
     pub fn new(boundary: Aabb<F>, vec: Vec<T>) -> Self {
         debug_assert!(N > 0, "The size should be a least 1");
 
         let mut result = Self {
-            vec,
+            elems: vec.into(),
             base_node: Node::empty(boundary),
+            query_mode: QueryMode::default(),
+            max_depth: DEFAULT_MAX_DEPTH,
         };
         result.rebuild_fit();
         result
     }
 
+    ///Builds the tree from all the elements at once, in O(n log n): the points are sorted by the
+    ///Morton code of their path in the tree (Z-order), then each node takes a contiguous run of them.
+    ///The tree is the same as if the elements were inserted one by one.
+    ///
+    ///The element `i` of `slice` can be found using the `i`-th handle of `iter_with_handles`.
+    ///`boundary` grows to contain all the elements.
+    pub fn from_slice(boundary: Aabb<F>, slice: &[T]) -> Self
+    where
+        T: Clone,
+    {
+        Self::new(boundary, slice.to_vec())
+    }
+
+    pub fn with_query_mode(mut self, query_mode: QueryMode) -> Self {
+        self.query_mode = query_mode;
+        self
+    }
+
+    pub fn set_query_mode(&mut self, query_mode: QueryMode) {
+        self.query_mode = query_mode;
+    }
+
+    pub fn query_mode(&self) -> QueryMode {
+        self.query_mode
+    }
+
+    ///Maximum depth of the tree, the root being at depth 1. Panics if `max_depth` is 0.
+    ///Applies to the next insertions, call `rebuild` to apply it to the whole tree.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.set_max_depth(max_depth);
+        self
+    }
+
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        assert!(max_depth > 0, "The max depth should be a least 1");
+        self.max_depth = max_depth;
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
     pub fn len(&self) -> usize {
-        self.vec.len()
+        self.elems.len()
     }
 
     pub fn is_empty(&self) -> bool {
@@ -70,61 +143,282 @@ impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize> Quadtree<F, T, N>
         self.base_node.depth()
     }
 
-    pub fn iter(&self) -> std::slice::Iter<'_, T> {
-        self.vec.iter()
+    ///Summary of all the elements of the tree.
+    pub fn summary(&self) -> &S {
+        &self.base_node.summary
     }
 
-    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
-        self.vec.iter_mut()
+    ///Recomputes every summary, to call after modifying elements in place
+    ///(through `get_mut`, `iter_mut` or the `map_*` functions) without moving them in the tree.
+    pub fn refresh_summaries(&mut self) {
+        self.base_node.refresh_summary(&self.elems);
     }
 
-    pub fn insert(&mut self, elem: T) -> Result<(), QuadtreeError<F>> {
-        let i = self.vec.len();
-        let i_p = IndexPoint::new(elem.x(), elem.y(), i);
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.elems.get(handle)
+    }
 
-        if !self.base_node.boundary.contain_pt(i_p.into_point()) {
+    ///After moving the element, call `update_position` to move it in the tree.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.elems.get_mut(handle)
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.elems.contains(handle)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.elems.values()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.elems.values_mut()
+    }
+
+    pub fn iter_with_handles(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.elems.iter()
+    }
+
+    pub fn iter_mut_with_handles(&mut self) -> impl Iterator<Item = (Handle, &mut T)> {
+        self.elems.iter_mut()
+    }
+
+    pub fn insert(&mut self, elem: T) -> Result<Handle, QuadtreeError<F>> {
+        let (x, y) = (elem.x(), elem.y());
+
+        if !self.base_node.boundary.contain_pt((x, y).as_point()) {
             return Err(QuadtreeError::OutOfBoundary(
                 self.base_node.boundary,
-                (i_p.x, i_p.y),
+                (x, y),
             ));
         }
 
-        self.vec.push(elem);
+        let handle = self.elems.insert(elem);
+        let i_p = IndexPoint::new(x, y, handle.index());
 
-        self.base_node.insert(i_p)
-          .unwrap_or_else(|_| panic!("something went wrong in QuadTree::insert: could not insert the value, even if it is in the Tree boundary ({:?}), the Tchebychev distance from the center is ({:?}), OOB : {}\n\t=>",
-          self.base_node.boundary,self.base_node.boundary.tchebychev_dist(i_p.into_point()),self.base_node.boundary.tchebychev_dist(i_p.into_point()) > self.base_node.boundary.half_dim));
+        self.base_node.insert(i_p, self.max_depth)
+          .unwrap_or_else(|_| panic!("something went wrong in QuadTree::insert: could not insert the value, even if it is in the Tree boundary ({:?}), the point is ({:?}), OOB : {}\n\t=>",
+          self.base_node.boundary,i_p.into_point(),!self.base_node.boundary.contain_pt(i_p.into_point())));
+        self.base_node
+            .refresh_summaries_at(i_p.into_point(), &self.elems);
 
-        Ok(())
+        Ok(handle)
     }
 
-    pub fn insert_fit(&mut self, elem: T) {
-        let i = self.vec.len();
-        let i_p = IndexPoint {
-            x: elem.x(),
-            y: elem.y(),
-            i,
-        };
-
-        self.vec.push(elem);
+    pub fn insert_fit(&mut self, elem: T) -> Handle {
+        let (x, y) = (elem.x(), elem.y());
+        let handle = self.elems.insert(elem);
+        let i_p = IndexPoint::new(x, y, handle.index());
 
-        if self.base_node.insert(i_p).is_err() {
+        if self.base_node.insert(i_p, self.max_depth).is_ok() {
+            self.base_node
+                .refresh_summaries_at(i_p.into_point(), &self.elems);
+        } else {
             self.rebuild_fit();
         }
+        handle
+    }
+
+    ///Removes the element of `handle` and returns it, `None` if the handle is no longer valid.
+    ///Other handles stay valid.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let elem = self.elems.remove(handle)?;
+
+        let i_p = self
+            .base_node
+            .find(handle.index(), elem.as_point())
+            .expect("something went wrong in QuadTree::remove: the element is not in the tree");
+        self.base_node.remove_at(i_p);
+        self.base_node
+            .refresh_summaries_at(i_p.into_point(), &self.elems);
+
+        Some(elem)
+    }
+
+    ///Moves the element of `handle` in the tree to its current position,
+    ///to call after modifying it through `get_mut` or `iter_mut`.
+    ///On error, the element keeps its previous place in the tree.
+    pub fn update_position(&mut self, handle: Handle) -> Result<(), QuadtreeError<F>> {
+        let elem = self
+            .elems
+            .get(handle)
+            .ok_or(QuadtreeError::InvalidHandle(handle))?;
+        let new_i_p = IndexPoint::new(elem.x(), elem.y(), handle.index());
+
+        if !new_i_p.into_point().as_valid_coord() {
+            return Err(QuadtreeError::InvalidCoord((new_i_p.x, new_i_p.y)));
+        }
+        if !self.base_node.boundary.contain_pt(new_i_p.into_point()) {
+            return Err(QuadtreeError::OutOfBoundary(
+                self.base_node.boundary,
+                (new_i_p.x, new_i_p.y),
+            ));
+        }
+
+        let old_i_p = self
+            .base_node
+            .find(handle.index(), new_i_p.into_point())
+            .expect(
+                "something went wrong in QuadTree::update_position: the element is not in the tree",
+            );
+        self.base_node.remove_at(old_i_p);
+        self.base_node
+            .refresh_summaries_at(old_i_p.into_point(), &self.elems);
+        self.base_node.insert(new_i_p, self.max_depth)?;
+        self.base_node
+            .refresh_summaries_at(new_i_p.into_point(), &self.elems);
+        Ok(())
     }
 
     pub fn query_range(&self, range: Aabb<F>) -> Vec<&T> {
         let mut result = vec![];
-        for i_p in self.base_node.query_range(range) {
-            result.push(&self.vec[i_p]);
+        self.query_range_into(range, &mut result);
+        result
+    }
+
+    ///Same as `query_range`, but pushes the elements into `result` to reuse its allocation.
+    ///`result` is not cleared.
+    pub fn query_range_into<'a>(&'a self, range: Aabb<F>, result: &mut Vec<&'a T>) {
+        result.extend(self.query_range_iter(range));
+    }
+
+    ///Lazy version of `query_range`.
+    pub fn query_range_iter(&self, range: Aabb<F>) -> QueryRangeIter<'_, F, T, N, S> {
+        self.query_range_iter_with(range, QueryStack::new())
+    }
+
+    ///Lazy version of `query_range` using the allocation of `stack`,
+    ///get it back with `QueryRangeIter::into_stack` for the next query.
+    pub fn query_range_iter_with<'a>(
+        &'a self,
+        range: Aabb<F>,
+        mut stack: QueryStack<'a, F, N, S>,
+    ) -> QueryRangeIter<'a, F, T, N, S> {
+        stack.nodes.clear();
+        stack.nodes.push(&self.base_node);
+
+        QueryRangeIter {
+            elems: &self.elems,
+            range,
+            query_mode: self.query_mode,
+            stack,
+            leaf: [].iter().chain([].iter()),
         }
+    }
 
-        result
+    ///Elements at most `radius` away from `center`.
+    pub fn query_circle<P: As2dPoint<F>>(&self, center: P, radius: F) -> Vec<&T> {
+        self.query_shape(&Circle::new(center, radius))
+    }
+
+    ///Elements in the rectangle going from `min` to `max`.
+    pub fn query_rect<P: As2dPoint<F>, U: As2dPoint<F>>(&self, min: P, max: U) -> Vec<&T> {
+        self.query_shape(&Aabb::from_min_max(min, max))
+    }
+
+    ///Elements contained in `shape`, nodes the shape does not intersect are skipped.
+    pub fn query_shape<Sh: Shape2d<F>>(&self, shape: &Sh) -> Vec<&T> {
+        self.base_node
+            .query_shape(shape)
+            .into_iter()
+            .map(|i| self.elem_at(i))
+            .collect()
+    }
+
+    ///Closest element to `point`, with its distance.
+    pub fn nearest<P: As2dPoint<F>>(&self, point: P) -> Option<(&T, F)> {
+        self.nearest_within(point, F::infinity())
+    }
+
+    ///Closest element to `point` that is at most `max_dist` away, with its distance.
+    pub fn nearest_within<P: As2dPoint<F>>(&self, point: P, max_dist: F) -> Option<(&T, F)> {
+        self.k_nearest_within(point, 1, max_dist).pop()
+    }
+
+    ///The `k` closest elements to `point`, sorted from the closest to the farthest, with their distances.
+    pub fn k_nearest<P: As2dPoint<F>>(&self, point: P, k: usize) -> Vec<(&T, F)> {
+        self.k_nearest_within(point, k, F::infinity())
+    }
+
+    ///The `k` closest elements to `point` that are at most `max_dist` away,
+    ///sorted from the closest to the farthest, with their distances.
+    ///Empty if `max_dist` is negative or NaN.
+    pub fn k_nearest_within<P: As2dPoint<F>>(
+        &self,
+        point: P,
+        k: usize,
+        max_dist: F,
+    ) -> Vec<(&T, F)> {
+        if max_dist.is_nan() || max_dist < F::zero() {
+            return vec![];
+        }
+        self.base_node
+            .k_nearest(point.as_point(), k, max_dist * max_dist)
+            .into_iter()
+            .map(|(i, dist_sq)| (self.elem_at(i), dist_sq.sqrt()))
+            .collect()
+    }
+
+    ///Elements at most `radius` away from the ray going from `origin` in the direction `dir`,
+    ///up to `max_dist`. The hits are sorted by their distance along the ray, given with them.
+    pub fn raycast<P: As2dPoint<F>, D: As2dPoint<F>>(
+        &self,
+        origin: P,
+        dir: D,
+        max_dist: F,
+        radius: F,
+    ) -> Vec<(&T, F)> {
+        let Some(dir) = normalized(dir.as_point()) else {
+            return vec![];
+        };
+        self.base_node
+            .raycast(origin.as_point(), dir, max_dist, radius, false)
+            .into_iter()
+            .map(|(i, t)| (self.elem_at(i), t))
+            .collect()
+    }
+
+    ///First hit of `raycast`, nodes behind it are not visited.
+    pub fn raycast_first<P: As2dPoint<F>, D: As2dPoint<F>>(
+        &self,
+        origin: P,
+        dir: D,
+        max_dist: F,
+        radius: F,
+    ) -> Option<(&T, F)> {
+        let dir = normalized(dir.as_point())?;
+        self.base_node
+            .raycast(origin.as_point(), dir, max_dist, radius, true)
+            .first()
+            .map(|(i, t)| (self.elem_at(*i), *t))
+    }
+
+    ///Elements at most `radius` away from the segment `[start, end]`,
+    ///sorted by their distance from `start` along the segment, given with them.
+    pub fn query_segment<P: As2dPoint<F>, U: As2dPoint<F>>(
+        &self,
+        start: P,
+        end: U,
+        radius: F,
+    ) -> Vec<(&T, F)> {
+        let (start, end) = (start.as_point(), end.as_point());
+        let dir = Point {
+            x: end.x - start.x,
+            y: end.y - start.y,
+        };
+        if dir.x == F::zero() && dir.y == F::zero() {
+            return self
+                .query_circle((start.x, start.y), radius)
+                .into_iter()
+                .map(|elem| (elem, F::zero()))
+                .collect();
+        }
+        self.raycast((start.x, start.y), (dir.x, dir.y), start.dist(end), radius)
     }
 
     pub fn map_query_range(&mut self, range: Aabb<F>, map: impl Fn(&mut T)) {
-        for i_point in self.base_node.query_range(range) {
-            map(&mut self.vec[i_point]);
+        for i_point in self.base_node.query_range(range, self.query_mode) {
+            map(self.elem_at_mut(i_point));
         }
     }
 
@@ -133,20 +427,23 @@ impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize> Quadtree<F, T, N>
         range_mapping: impl Fn(&T) -> Aabb<F>,
         map: impl Fn(&mut T, &mut T),
     ) {
-        for i in 0..self.vec.len() {
-            let range = self.base_node.query_range(range_mapping(&self.vec[i]));
+        for i in 0..self.elems.slot_len() {
+            let Some(elem) = self.elems.get_by_index(i) else {
+                continue;
+            };
+            let range = self
+                .base_node
+                .query_range(range_mapping(elem), self.query_mode);
 
             for i_p in range {
                 match i_p.cmp(&i) {
                     std::cmp::Ordering::Greater => {
-                        let (split_i, split_p) = self.vec.split_at_mut(i_p);
-                        map(&mut split_i[i], &mut split_p[0]);
-                        // println!("2. \t=> searching next")
+                        let (elem_i, elem_p) = self.elems_at_mut(i, i_p);
+                        map(elem_i, elem_p);
                     }
                     std::cmp::Ordering::Less => {
-                        let (split_p, split_i) = self.vec.split_at_mut(i);
-                        map(&mut split_p[i_p], &mut split_i[0]);
-                        // println!("2. \t=> searching next")
+                        let (elem_p, elem_i) = self.elems_at_mut(i_p, i);
+                        map(elem_p, elem_i);
                     }
                     _ => (),
                 };
@@ -154,6 +451,28 @@ impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize> Quadtree<F, T, N>
         }
     }
 
+    ///For each element, `accumulate` is called with every other element in `range_mapping(elem)`,
+    ///then `apply` gives it the accumulated value.
+    ///All the accumulations are done before applying any of them, so every element sees the others
+    ///unmodified. The tree is rebuilt afterward, as the elements may have moved.
+    pub fn accumulate_with_elem_in_range<A: Default>(
+        &mut self,
+        range_mapping: impl Fn(&T) -> Aabb<F>,
+        accumulate: impl Fn(&mut A, &T, &T),
+        apply: impl Fn(&mut T, A),
+    ) {
+        let accumulations: Vec<Option<A>> = (0..self.elems.slot_len())
+            .map(|i| self.accumulate_at(i, &range_mapping, &accumulate))
+            .collect();
+
+        for (i, acc) in accumulations.into_iter().enumerate() {
+            if let Some(acc) = acc {
+                apply(self.elem_at_mut(i), acc);
+            }
+        }
+        self.rebuild_after_move();
+    }
+
     //Horible name
     ///For each point in the quadtree :
     /// 1. first_map(point)  
@@ -170,151 +489,252 @@ impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize> Quadtree<F, T, N>
         let mut new_base_node = Node::empty(self.base_node.boundary);
         let mut failed_to_insert = false;
 
-        for i in 0..self.vec.len() {
-            let range = self.base_node.query_range(range_mapping(&self.vec[i]));
+        for i in 0..self.elems.slot_len() {
+            let Some(elem) = self.elems.get_by_index(i) else {
+                continue;
+            };
+            let range = self
+                .base_node
+                .query_range(range_mapping(elem), self.query_mode);
 
-            first_map(&mut self.vec[i]);
+            first_map(self.elem_at_mut(i));
 
             for i_p in range {
                 if i_p > i {
-                    let (left, right) = self.vec.split_at_mut(i_p);
-                    map_with_other(&mut left[i], &mut right[0]);
+                    let (left, right) = self.elems_at_mut(i, i_p);
+                    map_with_other(left, right);
                 }
             }
-            last_map(&mut self.vec[i]);
+            last_map(self.elem_at_mut(i));
 
             if !failed_to_insert {
+                let elem = self.elem_at(i);
                 let new_i_pt = IndexPoint {
-                    x: self.vec[i].x(),
-                    y: self.vec[i].y(),
+                    x: elem.x(),
+                    y: elem.y(),
                     i,
                 };
-                failed_to_insert = new_base_node.insert(new_i_pt).is_err();
+                failed_to_insert = new_base_node.insert(new_i_pt, self.max_depth).is_err();
             }
         }
         if !failed_to_insert {
             self.base_node = new_base_node;
+            self.base_node.refresh_summary(&self.elems);
         } else {
-            #[cfg(debug_assertions)]
-            eprintln!("rebuild the entiere tree");
-            self.rebuild_fit();
+            self.rebuild_after_move();
         }
     }
 
+    ///Rebuilds the tree with a boundary grown to contain all the elements, using a bulk load.
     pub fn rebuild_fit(&mut self) {
-        if !self
-            .vec
-            .iter()
-            .all(|p| self.base_node.boundary.contain_pt(p.as_point()))
-        {
-            let (min_x, max_x, min_y, max_y) = self.vec.iter().fold(
-                (
-                    F::infinity(),
-                    F::neg_infinity(),
-                    F::infinity(),
-                    F::neg_infinity(),
-                ),
-                |(min_x, max_x, min_y, max_y), elem| {
-                    (
-                        min_x.min(elem.x()) - F::one(),
-                        max_x.max(elem.x()) + F::one(),
-                        min_y.min(elem.y()) - F::one(),
-                        max_y.max(elem.y()) + F::one(),
-                    )
-                },
-            );
-
-            let width = max_x - min_x;
-            let height = max_y - min_y;
-            let two = F::one() + F::one();
-            let new_half_width = (width.max(height) / two).abs().max(F::epsilon());
-            let new_center = ((min_x + max_x) / two, (min_y + max_y) / two);
-
-            self.base_node = Node::empty(Aabb::new(new_center, new_half_width));
-        } else {
-            self.base_node = Node::empty(self.base_node.boundary);
-        }
-
-        for (i, elem) in self.vec.iter().enumerate() {
-            let elem_pt = IndexPoint {
-                x: elem.x(),
-                y: elem.y(),
-                i,
-            };
-            if let Err(e) = self.base_node.insert(elem_pt) {
-                match e {
-                    QuadtreeError::OutOfBoundary(_, _) => panic!(
-                        "QuadTree::rebuild went wrong : All points should fit after resize\n\t=>{e:?}"
-                    ),
-                    QuadtreeError::InvalidCoord(_) => {
-                        panic!(
-                            "QuadTree::rebuild went wrong : elem: {i} does not have valid coordinate\n\t=>{e:?}"
-                        )
-                    }
-                }
-            }
-        }
+        self.base_node = Node::empty(fitting_boundary(self.base_node.boundary, &self.elems));
+        self.bulk_load();
     }
 
     pub fn rebuild(&mut self) -> Result<(), QuadtreeError<F>> {
         let mut new_node = Node::empty(self.base_node.boundary);
 
-        for (i, elem) in self.vec.iter().enumerate() {
+        for (handle, elem) in self.elems.iter() {
             let i_pt = IndexPoint {
                 x: elem.x(),
                 y: elem.y(),
-                i,
+                i: handle.index(),
             };
 
-            new_node.insert(i_pt)?;
+            new_node.insert(i_pt, self.max_depth)?;
         }
         self.base_node = new_node;
+        self.base_node.refresh_summary(&self.elems);
         Ok(())
     }
 
     pub fn change_bounds(&mut self, new_bound: Aabb<F>) -> Result<(), QuadtreeError<F>> {
         let mut new_node = Node::empty(new_bound);
-        for (i, elem) in self.vec.iter().enumerate() {
+        for (handle, elem) in self.elems.iter() {
             let elem_pt = IndexPoint {
                 x: elem.x(),
                 y: elem.y(),
-                i,
+                i: handle.index(),
             };
 
-            new_node.insert(elem_pt)?;
+            new_node.insert(elem_pt, self.max_depth)?;
         }
 
         self.base_node = new_node;
+        self.base_node.refresh_summary(&self.elems);
 
         Ok(())
     }
+
+    ///replaces the nodes, all the elements have to be in the boundary
+    fn bulk_load(&mut self) {
+        let boundary = self.base_node.boundary;
+        let mut points: Vec<_> = self
+            .elems
+            .iter()
+            .map(|(handle, elem)| {
+                let i_p = IndexPoint::new(elem.x(), elem.y(), handle.index());
+                if !i_p.into_point().as_valid_coord() {
+                    panic!(
+                        "QuadTree::rebuild went wrong : elem: {} does not have valid coordinate",
+                        i_p.i
+                    )
+                }
+                (morton_code(boundary, i_p.into_point()), i_p)
+            })
+            .collect();
+        points.sort_unstable_by_key(|(code, _)| *code);
+
+        self.base_node = Node::from_sorted(boundary, &points, 1, self.max_depth);
+        self.base_node.refresh_summary(&self.elems);
+    }
+
+    fn accumulate_at<A: Default>(
+        &self,
+        i: usize,
+        range_mapping: &impl Fn(&T) -> Aabb<F>,
+        accumulate: &impl Fn(&mut A, &T, &T),
+    ) -> Option<A> {
+        let elem = self.elems.get_by_index(i)?;
+        let mut acc = A::default();
+
+        for other in self.query_range_iter(range_mapping(elem)) {
+            if !std::ptr::eq(elem, other) {
+                accumulate(&mut acc, elem, other);
+            }
+        }
+        Some(acc)
+    }
+
+    ///rebuilds the tree, growing its boundary if an element moved out of it
+    fn rebuild_after_move(&mut self) {
+        if self.rebuild().is_err() {
+            self.rebuild_fit();
+        }
+    }
+
+    ///element of a slot referenced by the tree
+    #[inline(always)]
+    fn elem_at(&self, i: usize) -> &T {
+        self.elems
+            .get_by_index(i)
+            .expect("something went wrong in QuadTree: the tree refers to an empty slot")
+    }
+
+    #[inline(always)]
+    fn elem_at_mut(&mut self, i: usize) -> &mut T {
+        self.elems
+            .get_mut_by_index(i)
+            .expect("something went wrong in QuadTree: the tree refers to an empty slot")
+    }
+
+    #[inline(always)]
+    fn elems_at_mut(&mut self, i: usize, j: usize) -> (&mut T, &mut T) {
+        self.elems
+            .get2_mut_by_index(i, j)
+            .expect("something went wrong in QuadTree: the tree refers to an empty slot")
+    }
+}
+
+///`boundary` if it contains all the elements, else `boundary` grown to contain them, plus a margin
+fn fitting_boundary<F: Float + Copy + Debug, T: As2dPoint<F>>(
+    boundary: Aabb<F>,
+    elems: &SlotMap<T>,
+) -> Aabb<F> {
+    if elems.values().all(|p| boundary.contain_pt(p.as_point())) {
+        return boundary;
+    }
+    let (min, max) = (boundary.min(), boundary.max());
+    let (min, max) = grown_bounds(
+        [min.x, min.y],
+        [max.x, max.y],
+        elems.values().map(|elem| [elem.x(), elem.y()]),
+    );
+
+    Aabb::from_min_max((min[0], min[1]), (max[0], max[1]))
+}
+
+///Nodes left to visit by a [`QueryRangeIter`], kept between queries to reuse its allocation.
+#[derive(Debug)]
+pub struct QueryStack<'a, F: Float + Copy + Debug, const N: usize, S = ()> {
+    nodes: Vec<&'a Node<F, N, S>>,
+}
+
+impl<F: Float + Copy + Debug, const N: usize, S> QueryStack<'_, F, N, S> {
+    pub fn new() -> Self {
+        Self { nodes: vec![] }
+    }
+}
+
+impl<F: Float + Copy + Debug, const N: usize, S> Default for QueryStack<'_, F, N, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+///Iterator over the elements of a [`Quadtree`] in a range, see `Quadtree::query_range_iter`.
+#[derive(Debug)]
+pub struct QueryRangeIter<'a, F: Float + Copy + Debug, T, const N: usize, S = ()> {
+    elems: &'a SlotMap<T>,
+    range: Aabb<F>,
+    query_mode: QueryMode,
+    stack: QueryStack<'a, F, N, S>,
+    leaf: LeafIter<'a, IndexPoint<F>>,
+}
+
+impl<'a, F: Float + Copy + Debug, T, const N: usize, S> QueryRangeIter<'a, F, T, N, S> {
+    pub fn into_stack(mut self) -> QueryStack<'a, F, N, S> {
+        self.stack.nodes.clear();
+        self.stack
+    }
+}
+
+impl<'a, F: Float + Copy + Debug, T, const N: usize, S> Iterator
+    for QueryRangeIter<'a, F, T, N, S>
+{
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(i_p) = self.leaf.next() {
+                if self.query_mode == QueryMode::Exact && !self.range.contain_pt(i_p.into_point()) {
+                    continue;
+                }
+                return Some(self.elems.get_by_index(i_p.i).expect(
+                    "something went wrong in QueryRangeIter: the tree refers to an empty slot",
+                ));
+            }
+
+            let curr_node = self.stack.nodes.pop()?;
+            if !curr_node.boundary.intersect(self.range) {
+                continue;
+            }
+            match &curr_node.data {
+                NodeData::Child(child) => self.stack.nodes.extend(child.children()),
+                NodeData::Leaf(leaf) => self.leaf = leaf.iter(),
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
-struct Node<F: Float + Copy + Debug, const N: usize> {
+struct Node<F: Float + Copy + Debug, const N: usize, S> {
     boundary: Aabb<F>,
-    data: NodeData<F, N>,
+    data: NodeData<F, N, S>,
+    summary: S,
 }
 
-impl<F: Float + Copy + Debug, const N: usize> Node<F, N> {
+impl<F: Float + Copy + Debug, const N: usize, S: Default> Node<F, N, S> {
     fn empty(boundary: Aabb<F>) -> Self {
         Self {
             boundary,
-            data: NodeData::Leaf(NodeLeafData {
-                points: ArrayVec::new(),
-            }),
+            data: NodeData::new_leaf(ArrayVec::new()),
+            summary: S::default(),
         }
     }
 
-    fn insert(&mut self, p_i: IndexPoint<F>) -> Result<(), QuadtreeError<F>> {
-        #[cfg(debug_assertions)]
-        const MAX_LOOP: usize = 500_000;
-        #[cfg(all(test, not(debug_assertions)))]
-        const MAX_LOOP: usize = 200_000;
-
-        #[cfg(debug_assertions)]
-        let mut it_num = 0;
-
+    ///`max_depth` counts this node, leaves at this depth put the elements past `N` in their overflow
+    fn insert(&mut self, p_i: IndexPoint<F>, max_depth: usize) -> Result<(), QuadtreeError<F>> {
         let pt = p_i.into_point();
 
         if !pt.as_valid_coord() {
@@ -322,31 +742,206 @@ impl<F: Float + Copy + Debug, const N: usize> Node<F, N> {
         }
         let mut curr_data = &mut self.data;
         let mut curr_bounds = self.boundary;
+        let mut depth = 1;
 
         while curr_bounds.contain_pt(pt) {
             match curr_data {
                 NodeData::Child(child) => {
                     let dir = curr_bounds.diag_pos_from_center(pt);
-                    (curr_data, curr_bounds) = child.get_child_mut(dir)
+                    (curr_data, curr_bounds) = child.get_child_mut(dir);
+                    depth += 1;
                 }
                 NodeData::Leaf(leaf) => {
-                    if leaf.points.is_full() {
-                        curr_data.subdivide_into_child_data(curr_bounds);
-                        continue;
-                    } else {
+                    if !leaf.points.is_full() {
                         leaf.points.push(p_i);
                         return Ok(());
+                    } else if depth >= max_depth || !leaf.overflow.is_empty() {
+                        //a leaf with an overflow was at max depth before a change of `max_depth`
+                        leaf.overflow.push(p_i);
+                        return Ok(());
+                    } else {
+                        curr_data.subdivide_into_child_data(curr_bounds);
                     }
                 }
             }
+        }
+        Err(QuadtreeError::OutOfBoundary(curr_bounds, (p_i.x, p_i.y)))
+    }
 
-            #[cfg(debug_assertions)]
-            {
-                it_num += 1;
-                debug_assert!(it_num < MAX_LOOP, "to much iteration")
+    ///builds the subtree at `depth` of the points sorted by `morton_code`, all under this node
+    fn from_sorted(
+        boundary: Aabb<F>,
+        points: &[(u64, IndexPoint<F>)],
+        depth: usize,
+        max_depth: usize,
+    ) -> Self {
+        if points.len() <= N || depth >= max_depth {
+            let split = points.len().min(N);
+            let mut leaf = NodeLeafData::default();
+            leaf.points
+                .extend(points[..split].iter().map(|(_, i_p)| *i_p));
+            leaf.overflow
+                .extend(points[split..].iter().map(|(_, i_p)| *i_p));
+            return Self {
+                boundary,
+                data: NodeData::Leaf(leaf),
+                summary: S::default(),
+            };
+        }
+        if depth > MORTON_LEVELS {
+            //past the precision of the codes, the rest is inserted
+            let mut node = Self::empty(boundary);
+            for (_, i_p) in points {
+                node.insert(*i_p, max_depth - depth + 1).expect(
+                    "something went wrong in QuadTree::bulk_load: the point is in the node",
+                );
             }
+            return node;
         }
-        Err(QuadtreeError::OutOfBoundary(curr_bounds, (p_i.x, p_i.y)))
+
+        //the children runs, in Morton order
+        let shift = 2 * (MORTON_LEVELS - depth);
+        let runs: [usize; 5] = std::array::from_fn(|k| {
+            points.partition_point(|(code, _)| ((code >> shift) & 3) < k as u64)
+        });
+        let [ul, ur, dr, dl] = boundary.subdivide();
+        let child = |dir: DiagonalDirection, bounds: Aabb<F>| {
+            let k = morton_index(dir);
+            Box::new(Self::from_sorted(
+                bounds,
+                &points[runs[k]..runs[k + 1]],
+                depth + 1,
+                max_depth,
+            ))
+        };
+
+        Self {
+            boundary,
+            data: NodeData::Child(NodeChildData {
+                up_right: child(DiagonalDirection::UpRight, ur),
+                up_left: child(DiagonalDirection::UpLeft, ul),
+                down_left: child(DiagonalDirection::DownLeft, dl),
+                down_right: child(DiagonalDirection::DownRight, dr),
+            }),
+            summary: S::default(),
+        }
+    }
+
+    ///looks for the point of index `i`, first in the leaf containing `hint`, then everywhere
+    fn find(&self, i: usize, hint: Point<F>) -> Option<IndexPoint<F>> {
+        self.leaf_at(hint)
+            .iter()
+            .find(|i_p| i_p.i == i)
+            .copied()
+            .or_else(|| self.find_anywhere(i))
+    }
+
+    fn find_anywhere(&self, i: usize) -> Option<IndexPoint<F>> {
+        let mut stack = vec![self];
+
+        while let Some(curr_node) = stack.pop() {
+            match &curr_node.data {
+                NodeData::Child(child) => stack.extend(child.children()),
+                NodeData::Leaf(leaf) => {
+                    if let Some(i_p) = leaf.iter().find(|i_p| i_p.i == i) {
+                        return Some(*i_p);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn leaf_at(&self, pt: Point<F>) -> &NodeLeafData<F, N> {
+        let mut curr_node = self;
+        loop {
+            match &curr_node.data {
+                NodeData::Child(child) => {
+                    curr_node = child.get_child(curr_node.boundary.diag_pos_from_center(pt))
+                }
+                NodeData::Leaf(leaf) => return leaf,
+            }
+        }
+    }
+
+    ///removes the point `i_p` (found with its stored coordinates), merging the nodes left under capacity
+    fn remove_at(&mut self, i_p: IndexPoint<F>) -> bool {
+        let dir = self.boundary.diag_pos_from_center(i_p.into_point());
+        match &mut self.data {
+            NodeData::Leaf(leaf) => leaf.remove(i_p.i),
+            NodeData::Child(child) => {
+                let removed = child.get_child_node_mut(dir).remove_at(i_p);
+                if removed {
+                    self.try_merge();
+                }
+                removed
+            }
+        }
+    }
+
+    ///turns the node back into a leaf if its children are leaves holding at most N points
+    fn try_merge(&mut self) {
+        let NodeData::Child(child) = &self.data else {
+            return;
+        };
+
+        let mut points = ArrayVec::new();
+        for node in child.children() {
+            match &node.data {
+                NodeData::Leaf(leaf) => {
+                    if !leaf.overflow.is_empty()
+                        || points.try_extend_from_slice(&leaf.points).is_err()
+                    {
+                        return;
+                    }
+                }
+                NodeData::Child(_) => return,
+            }
+        }
+
+        self.data = NodeData::new_leaf(points);
+    }
+
+    ///recomputes the summaries of the whole subtree
+    fn refresh_summary<T>(&mut self, elems: &SlotMap<T>)
+    where
+        S: Summary<T>,
+    {
+        self.summary = match &mut self.data {
+            NodeData::Child(child) => {
+                let mut summary = S::default();
+                for node in child.children_mut() {
+                    node.refresh_summary(elems);
+                    summary = summary.combine(&node.summary);
+                }
+                summary
+            }
+            NodeData::Leaf(leaf) => leaf.summary(elems),
+        };
+    }
+
+    ///recomputes the summaries on the path of `pt`, after a change at this point.
+    ///Leaves out of the path may come from a subdivision and are recomputed too.
+    fn refresh_summaries_at<T>(&mut self, pt: Point<F>, elems: &SlotMap<T>)
+    where
+        S: Summary<T>,
+    {
+        let on_path = child_index(self.boundary.diag_pos_from_center(pt));
+        self.summary = match &mut self.data {
+            NodeData::Child(child) => {
+                let mut summary = S::default();
+                for (k, node) in child.children_mut().into_iter().enumerate() {
+                    if k == on_path {
+                        node.refresh_summaries_at(pt, elems);
+                    } else if let NodeData::Leaf(leaf) = &node.data {
+                        node.summary = leaf.summary(elems);
+                    }
+                    summary = summary.combine(&node.summary);
+                }
+                summary
+            }
+            NodeData::Leaf(leaf) => leaf.summary(elems),
+        };
     }
 
     fn depth(&self) -> usize {
@@ -365,7 +960,7 @@ impl<F: Float + Copy + Debug, const N: usize> Node<F, N> {
         }
     }
 
-    fn query_range(&self, range: Aabb<F>) -> Vec<usize> {
+    fn query_range(&self, range: Aabb<F>, query_mode: QueryMode) -> Vec<usize> {
         //iterative
 
         let mut result = Vec::new();
@@ -383,8 +978,34 @@ impl<F: Float + Copy + Debug, const N: usize> Node<F, N> {
                     stack.push(&child.down_left);
                 }
                 NodeData::Leaf(leaf) => {
-                    for i_p in &leaf.points {
-                        result.push(i_p.i);
+                    for i_p in leaf.iter() {
+                        if query_mode == QueryMode::BroadPhase || range.contain_pt(i_p.into_point())
+                        {
+                            result.push(i_p.i);
+                        }
+                    }
+                }
+            }
+        }
+
+        result
+    }
+
+    fn query_shape<Sh: Shape2d<F>>(&self, shape: &Sh) -> Vec<usize> {
+        let mut result = Vec::new();
+        let mut stack = vec![self];
+
+        while let Some(curr_node) = stack.pop() {
+            if !shape.intersects_aabb(curr_node.boundary) {
+                continue;
+            }
+            match &curr_node.data {
+                NodeData::Child(child) => stack.extend(child.children()),
+                NodeData::Leaf(leaf) => {
+                    for i_p in leaf.iter() {
+                        if shape.contains_point(i_p.into_point()) {
+                            result.push(i_p.i);
+                        }
                     }
                 }
             }
@@ -392,48 +1013,205 @@ impl<F: Float + Copy + Debug, const N: usize> Node<F, N> {
 
         result
     }
+
+    ///best-first walk, returns (index, squared distance) sorted by distance
+    fn k_nearest(&self, point: Point<F>, k: usize, max_dist_sq: F) -> Vec<(usize, F)> {
+        let mut result = Vec::with_capacity(k);
+        if k == 0 {
+            return result;
+        }
+
+        let mut heap = BinaryHeap::new();
+        heap.push(NearestEntry {
+            key: self.boundary.dist_sq_to_pt(point),
+            item: NearestItem::Node(self),
+        });
+
+        while let Some(NearestEntry { key: dist_sq, item }) = heap.pop() {
+            if dist_sq > max_dist_sq {
+                break;
+            }
+            match item {
+                NearestItem::Elem(i) => {
+                    result.push((i, dist_sq));
+                    if result.len() == k {
+                        break;
+                    }
+                }
+                NearestItem::Node(node) => match &node.data {
+                    NodeData::Child(child) => {
+                        for child in child.children() {
+                            heap.push(NearestEntry {
+                                key: child.boundary.dist_sq_to_pt(point),
+                                item: NearestItem::Node(child),
+                            });
+                        }
+                    }
+                    NodeData::Leaf(leaf) => {
+                        for i_p in leaf.iter() {
+                            heap.push(NearestEntry {
+                                key: i_p.into_point().dist_sq(point),
+                                item: NearestItem::Elem(i_p.i),
+                            });
+                        }
+                    }
+                },
+            }
+        }
+
+        result
+    }
+}
+
+///ray traversal, returns (index, distance along the ray) sorted by distance.
+///The nodes are visited in the order the ray enters them (grown by `radius`),
+///a hit is always found after the nodes the ray enters before it.
+impl<F: Float + Copy + Debug, const N: usize, S> Node<F, N, S> {
+    fn raycast<'a>(
+        &'a self,
+        origin: Point<F>,
+        dir: Point<F>,
+        max_t: F,
+        radius: F,
+        first_only: bool,
+    ) -> Vec<(usize, F)> {
+        let mut hits = vec![];
+        let mut heap = BinaryHeap::new();
+        let push_node = |heap: &mut BinaryHeap<NearestEntry<F, &'a Self>>, node: &'a Self| {
+            let grown = node.boundary.expand(radius);
+            if let Some((t_enter, _)) = grown.ray_intersection(origin, dir, max_t) {
+                heap.push(NearestEntry {
+                    key: t_enter,
+                    item: NearestItem::Node(node),
+                });
+            }
+        };
+        push_node(&mut heap, self);
+
+        while let Some(NearestEntry { key: t_enter, item }) = heap.pop() {
+            if first_only && hits.first().is_some_and(|(_, t)| *t <= t_enter) {
+                break;
+            }
+            let NearestItem::Node(node) = item else {
+                continue;
+            };
+            match &node.data {
+                NodeData::Child(child) => {
+                    for child in child.children() {
+                        push_node(&mut heap, child);
+                    }
+                }
+                NodeData::Leaf(leaf) => {
+                    for i_p in leaf.iter() {
+                        let (dx, dy) = (i_p.x - origin.x, i_p.y - origin.y);
+                        let t = (dx * dir.x + dy * dir.y).max(F::zero()).min(max_t);
+                        let closest = Point {
+                            x: origin.x + dir.x * t,
+                            y: origin.y + dir.y * t,
+                        };
+                        if closest.dist_sq(i_p.into_point()) <= radius * radius {
+                            hits.push((i_p.i, t));
+                        }
+                    }
+                    if first_only {
+                        hits.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+                        hits.truncate(1);
+                    }
+                }
+            }
+        }
+
+        hits.sort_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        hits
+    }
+}
+
+#[inline(always)]
+fn normalized<F: Float + Copy>(v: Point<F>) -> Option<Point<F>> {
+    let len = (v.x * v.x + v.y * v.y).sqrt();
+    (len > F::zero() && len.is_finite()).then(|| Point {
+        x: v.x / len,
+        y: v.y / len,
+    })
 }
 
 #[derive(Debug, Clone)]
-enum NodeData<F: Float + Copy + Debug, const N: usize> {
-    Child(NodeChildData<F, N>),
+enum NodeData<F: Float + Copy + Debug, const N: usize, S> {
+    Child(NodeChildData<F, N, S>),
     Leaf(NodeLeafData<F, N>),
 }
 
-impl<F: Float + Copy + Debug, const N: usize> NodeData<F, N> {
+impl<F: Float + Copy + Debug, const N: usize, S: Default> NodeData<F, N, S> {
     fn new_leaf(points: ArrayVec<IndexPoint<F>, N>) -> Self {
-        Self::Leaf(NodeLeafData { points })
+        Self::Leaf(NodeLeafData {
+            points,
+            overflow: vec![],
+        })
     }
 
+    ///turns a leaf into a node with four leaf children, does nothing on a node with children.
+    ///The points are moved out first, so a panic leaves an empty leaf and not a broken node.
     #[inline(always)]
     fn subdivide_into_child_data(&mut self, boundary: Aabb<F>) {
-        //Should review this function
-        let uninit: &mut MaybeUninit<NodeData<F, N>> = unsafe { core::mem::transmute(self) };
-        let prev = core::mem::replace(uninit, MaybeUninit::uninit());
-
-        let assumed_init = unsafe { prev.assume_init() };
-
-        let new = match assumed_init {
-            NodeData::Child(_) => assumed_init,
-            NodeData::Leaf(leaf) => NodeData::Child(leaf.subdivide_into_child_data(boundary)),
-        };
-
-        let _ = core::mem::replace(uninit, MaybeUninit::new(new));
+        if let NodeData::Leaf(leaf) = self {
+            let leaf = std::mem::take(leaf);
+            *self = NodeData::Child(leaf.subdivide_into_child_data(boundary));
+        }
     }
 }
 
 #[derive(Debug, Clone)]
-struct NodeChildData<F: Float + Copy + Debug, const N: usize> {
-    up_right: Box<Node<F, N>>,
-    up_left: Box<Node<F, N>>,
+struct NodeChildData<F: Float + Copy + Debug, const N: usize, S> {
+    up_right: Box<Node<F, N, S>>,
+    up_left: Box<Node<F, N, S>>,
 
-    down_left: Box<Node<F, N>>,
-    down_right: Box<Node<F, N>>,
+    down_left: Box<Node<F, N, S>>,
+    down_right: Box<Node<F, N, S>>,
 }
 
-impl<F: Float + Copy + Debug, const N: usize> NodeChildData<F, N> {
+impl<F: Float + Copy + Debug, const N: usize, S> NodeChildData<F, N, S> {
+    #[inline(always)]
+    fn children(&self) -> [&Node<F, N, S>; 4] {
+        [
+            &self.up_right,
+            &self.up_left,
+            &self.down_left,
+            &self.down_right,
+        ]
+    }
+
+    #[inline(always)]
+    fn children_mut(&mut self) -> [&mut Node<F, N, S>; 4] {
+        [
+            &mut self.up_right,
+            &mut self.up_left,
+            &mut self.down_left,
+            &mut self.down_right,
+        ]
+    }
+
+    #[inline(always)]
+    fn get_child(&self, dir: DiagonalDirection) -> &Node<F, N, S> {
+        match dir {
+            DiagonalDirection::UpRight => &self.up_right,
+            DiagonalDirection::UpLeft => &self.up_left,
+            DiagonalDirection::DownLeft => &self.down_left,
+            DiagonalDirection::DownRight => &self.down_right,
+        }
+    }
+
+    #[inline(always)]
+    fn get_child_node_mut(&mut self, dir: DiagonalDirection) -> &mut Node<F, N, S> {
+        match dir {
+            DiagonalDirection::UpRight => &mut self.up_right,
+            DiagonalDirection::UpLeft => &mut self.up_left,
+            DiagonalDirection::DownLeft => &mut self.down_left,
+            DiagonalDirection::DownRight => &mut self.down_right,
+        }
+    }
+
     #[inline(always)]
-    fn get_child_mut(&mut self, dir: DiagonalDirection) -> (&mut NodeData<F, N>, Aabb<F>) {
+    fn get_child_mut(&mut self, dir: DiagonalDirection) -> (&mut NodeData<F, N, S>, Aabb<F>) {
         match dir {
             DiagonalDirection::UpRight => (&mut self.up_right.data, self.up_right.boundary),
             DiagonalDirection::UpLeft => (&mut self.up_left.data, self.up_left.boundary),
@@ -443,13 +1221,18 @@ impl<F: Float + Copy + Debug, const N: usize> NodeChildData<F, N> {
     }
 }
 
-#[derive(Debug, Clone)]
-struct NodeLeafData<F: Float + Copy + Debug, const N: usize> {
-    points: ArrayVec<IndexPoint<F>, N>,
-}
+type NodeLeafData<F, const N: usize> = LeafData<IndexPoint<F>, N>;
 
 impl<F: Float + Copy + Debug, const N: usize> NodeLeafData<F, N> {
-    fn subdivide_into_child_data(self, boundary: Aabb<F>) -> NodeChildData<F, N> {
+    fn summary<T, S: Summary<T>>(&self, elems: &SlotMap<T>) -> S {
+        self.iter()
+            .filter_map(|i_p| elems.get_by_index(i_p.i))
+            .fold(S::default(), |summary, elem| {
+                summary.combine(&S::from_elem(elem))
+            })
+    }
+
+    fn subdivide_into_child_data<S: Default>(self, boundary: Aabb<F>) -> NodeChildData<F, N, S> {
         let [ul, ur, dr, dl] = boundary.subdivide();
         let [mut ur_p, mut ul_p, mut dl_p, mut dr_p] = [
             ArrayVec::new(),
@@ -471,18 +1254,22 @@ impl<F: Float + Copy + Debug, const N: usize> NodeLeafData<F, N> {
         let up_right = Box::new(Node {
             boundary: ur,
             data: NodeData::new_leaf(ur_p),
+            summary: S::default(),
         });
         let up_left = Box::new(Node {
             boundary: ul,
             data: NodeData::new_leaf(ul_p),
+            summary: S::default(),
         });
         let down_left = Box::new(Node {
             boundary: dl,
             data: NodeData::new_leaf(dl_p),
+            summary: S::default(),
         });
         let down_right = Box::new(Node {
             boundary: dr,
             data: NodeData::new_leaf(dr_p),
+            summary: S::default(),
         });
 
         NodeChildData {
@@ -493,3 +1280,48 @@ impl<F: Float + Copy + Debug, const N: usize> NodeLeafData<F, N> {
         }
     }
 }
+
+///number of levels in a `morton_code`
+const MORTON_LEVELS: usize = 32;
+
+///quadrants of `pt` from the root to the depth `MORTON_LEVELS`, two bits per level, the first
+///level in the high bits. The boxes are split like in the tree, so the codes follow its nodes.
+fn morton_code<F: Float + Copy + Debug>(boundary: Aabb<F>, pt: Point<F>) -> u64 {
+    let mut code = 0;
+    let mut curr_bounds = boundary;
+    for _ in 0..MORTON_LEVELS {
+        let dir = curr_bounds.diag_pos_from_center(pt);
+        code = (code << 2) | morton_index(dir) as u64;
+
+        let [ul, ur, dr, dl] = curr_bounds.subdivide();
+        curr_bounds = match dir {
+            DiagonalDirection::UpLeft => ul,
+            DiagonalDirection::UpRight => ur,
+            DiagonalDirection::DownRight => dr,
+            DiagonalDirection::DownLeft => dl,
+        };
+    }
+    code
+}
+
+///position of a child in Morton order (in a `morton_code` or an `ArenaQuadtree` block)
+#[inline(always)]
+fn morton_index(dir: DiagonalDirection) -> usize {
+    match dir {
+        DiagonalDirection::DownLeft => 0,
+        DiagonalDirection::DownRight => 1,
+        DiagonalDirection::UpLeft => 2,
+        DiagonalDirection::UpRight => 3,
+    }
+}
+
+///position of a child in the array returned by `NodeChildData::children`
+#[inline(always)]
+fn child_index(dir: DiagonalDirection) -> usize {
+    match dir {
+        DiagonalDirection::UpRight => 0,
+        DiagonalDirection::UpLeft => 1,
+        DiagonalDirection::DownLeft => 2,
+        DiagonalDirection::DownRight => 3,
+    }
+}
@@ -0,0 +1,36 @@
+use std::fmt::Debug;
+
+use num::Float;
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use super::{Aabb, As2dPoint, Quadtree, Summary};
+
+impl<F, T, const N: usize, S> Quadtree<F, T, N, S>
+where
+    F: Float + Copy + Debug + Send + Sync,
+    T: As2dPoint<F> + Send + Sync,
+    S: Summary<T> + Sync,
+{
+    ///Parallel version of `accumulate_with_elem_in_range`, giving the same results.
+    pub fn par_accumulate_with_elem_in_range<A: Default + Send>(
+        &mut self,
+        range_mapping: impl Fn(&T) -> Aabb<F> + Sync,
+        accumulate: impl Fn(&mut A, &T, &T) + Sync,
+        apply: impl Fn(&mut T, A) + Sync,
+    ) {
+        let accumulations: Vec<Option<A>> = (0..self.elems.slot_len())
+            .into_par_iter()
+            .map(|i| self.accumulate_at(i, &range_mapping, &accumulate))
+            .collect();
+
+        self.elems
+            .par_slots_mut()
+            .zip(accumulations)
+            .for_each(|(elem, acc)| {
+                if let (Some(elem), Some(acc)) = (elem, acc) {
+                    apply(elem, acc);
+                }
+            });
+        self.rebuild_after_move();
+    }
+}
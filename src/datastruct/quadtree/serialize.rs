@@ -0,0 +1,69 @@
+use std::fmt::Debug;
+
+use num::Float;
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+use super::{Aabb, As2dPoint, Node, Quadtree, QueryMode, Summary};
+use crate::datastruct::slotmap::SlotMap;
+
+///what is stored of a `Quadtree`, the nodes are rebuilt from the elements
+#[derive(Serialize)]
+struct QuadtreeRef<'a, F: Float + Copy, T> {
+    boundary: Aabb<F>,
+    query_mode: QueryMode,
+    max_depth: usize,
+    elems: &'a SlotMap<T>,
+}
+
+#[derive(Deserialize)]
+struct QuadtreeData<F: Float + Copy, T> {
+    boundary: Aabb<F>,
+    query_mode: QueryMode,
+    max_depth: usize,
+    elems: SlotMap<T>,
+}
+
+///Stores the boundary, the settings and the elements (handles stay valid), but not the nodes.
+impl<F, T, const N: usize, S> Serialize for Quadtree<F, T, N, S>
+where
+    F: Float + Copy + Debug + Serialize,
+    T: As2dPoint<F> + Serialize,
+    S: Summary<T>,
+{
+    fn serialize<Se: Serializer>(&self, serializer: Se) -> Result<Se::Ok, Se::Error> {
+        QuadtreeRef {
+            boundary: self.base_node.boundary,
+            query_mode: self.query_mode,
+            max_depth: self.max_depth,
+            elems: &self.elems,
+        }
+        .serialize(serializer)
+    }
+}
+
+///Rebuilds the nodes, fails if an element is out of the boundary.
+impl<'de, F, T, const N: usize, S> Deserialize<'de> for Quadtree<F, T, N, S>
+where
+    F: Float + Copy + Debug + Deserialize<'de>,
+    T: As2dPoint<F> + Deserialize<'de>,
+    S: Summary<T>,
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let data = QuadtreeData::<F, T>::deserialize(deserializer)?;
+        if !(data.boundary.half_width > F::zero() && data.boundary.half_height > F::zero()) {
+            return Err(D::Error::custom("the boundary should have a positive size"));
+        }
+        if data.max_depth == 0 {
+            return Err(D::Error::custom("the max depth should be a least 1"));
+        }
+
+        let mut result = Self {
+            elems: data.elems,
+            base_node: Node::empty(data.boundary),
+            query_mode: data.query_mode,
+            max_depth: data.max_depth,
+        };
+        result.rebuild().map_err(D::Error::custom)?;
+        Ok(result)
+    }
+}
@@ -0,0 +1,147 @@
+use std::fmt::Debug;
+
+use num::Float;
+
+use super::{Aabb, As2dPoint, Node, NodeData, Quadtree, Summary};
+
+///Shape of a [`Quadtree`], see `Quadtree::stats`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct QuadtreeStats {
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub empty_leaves: usize,
+    ///leaves holding more than `N` elements, at max depth
+    pub overflowing_leaves: usize,
+    ///`occupancy[k]` is the number of leaves holding `k` elements,
+    ///the overflowing leaves are counted in `occupancy[N]`
+    pub occupancy: Vec<usize>,
+    ///`nodes_per_depth[d]` is the number of nodes at depth `d + 1`
+    pub nodes_per_depth: Vec<usize>,
+}
+
+///Broken invariant found by `Quadtree::validate`.
+#[derive(Debug, Clone, Copy)]
+pub enum ValidationError<F: Float + Copy + Debug> {
+    ///a point is stored in a node not containing it
+    PointOutOfNode {
+        index: usize,
+        point: (F, F),
+        boundary: Aabb<F>,
+    },
+    ///an element is stored more than once
+    DuplicateIndex(usize),
+    ///an element is not in the nodes
+    MissingIndex(usize),
+    ///the nodes refer to an empty slot
+    DanglingIndex(usize),
+    ///a leaf has an overflow but is not full
+    UnfilledOverflow(Aabb<F>),
+}
+
+impl<F: Float + Copy + Debug> std::fmt::Display for ValidationError<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::PointOutOfNode {
+                index,
+                point,
+                boundary,
+            } => write!(
+                f,
+                "point {:?} of element {} is stored in {:?}, that does not contain it.",
+                point, index, boundary
+            ),
+            ValidationError::DuplicateIndex(index) => {
+                write!(f, "element {} is stored more than once.", index)
+            }
+            ValidationError::MissingIndex(index) => {
+                write!(f, "element {} is not stored in the nodes.", index)
+            }
+            ValidationError::DanglingIndex(index) => {
+                write!(f, "the nodes refer to {}, an empty slot.", index)
+            }
+            ValidationError::UnfilledOverflow(boundary) => {
+                write!(
+                    f,
+                    "the leaf {:?} has an overflow but is not full.",
+                    boundary
+                )
+            }
+        }
+    }
+}
+
+impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize, S: Summary<T>> Quadtree<F, T, N, S> {
+    pub fn stats(&self) -> QuadtreeStats {
+        let mut stats = QuadtreeStats {
+            occupancy: vec![0; N + 1],
+            ..Default::default()
+        };
+        let mut stack: Vec<(&Node<F, N, S>, usize)> = vec![(&self.base_node, 1)];
+
+        while let Some((curr_node, depth)) = stack.pop() {
+            stats.node_count += 1;
+            if stats.nodes_per_depth.len() < depth {
+                stats.nodes_per_depth.resize(depth, 0);
+            }
+            stats.nodes_per_depth[depth - 1] += 1;
+
+            match &curr_node.data {
+                NodeData::Child(child) => {
+                    stack.extend(child.children().map(|node| (node, depth + 1)));
+                }
+                NodeData::Leaf(leaf) => {
+                    stats.leaf_count += 1;
+                    stats.occupancy[leaf.points.len()] += 1;
+                    if leaf.points.is_empty() {
+                        stats.empty_leaves += 1;
+                    }
+                    if !leaf.overflow.is_empty() {
+                        stats.overflowing_leaves += 1;
+                    }
+                }
+            }
+        }
+        stats
+    }
+
+    ///Checks that every stored point lies in the boundary of its node,
+    ///and that every element is stored exactly once.
+    pub fn validate(&self) -> Result<(), ValidationError<F>> {
+        let mut seen = vec![false; self.elems.slot_len()];
+        let mut stack = vec![&self.base_node];
+
+        while let Some(curr_node) = stack.pop() {
+            match &curr_node.data {
+                NodeData::Child(child) => stack.extend(child.children()),
+                NodeData::Leaf(leaf) => {
+                    if !leaf.overflow.is_empty() && !leaf.points.is_full() {
+                        return Err(ValidationError::UnfilledOverflow(curr_node.boundary));
+                    }
+                    for i_p in leaf.iter() {
+                        if !curr_node.boundary.contain_pt(i_p.into_point()) {
+                            return Err(ValidationError::PointOutOfNode {
+                                index: i_p.i,
+                                point: (i_p.x, i_p.y),
+                                boundary: curr_node.boundary,
+                            });
+                        }
+                        match seen.get_mut(i_p.i) {
+                            Some(seen) if self.elems.get_by_index(i_p.i).is_some() => {
+                                if *seen {
+                                    return Err(ValidationError::DuplicateIndex(i_p.i));
+                                }
+                                *seen = true;
+                            }
+                            _ => return Err(ValidationError::DanglingIndex(i_p.i)),
+                        }
+                    }
+                }
+            }
+        }
+
+        match (0..seen.len()).find(|&i| !seen[i] && self.elems.get_by_index(i).is_some()) {
+            Some(i) => Err(ValidationError::MissingIndex(i)),
+            None => Ok(()),
+        }
+    }
+}
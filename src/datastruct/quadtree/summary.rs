@@ -0,0 +1,134 @@
+use std::fmt::Debug;
+
+use num::Float;
+
+use super::{Node, NodeData, Quadtree};
+use crate::datastruct::points::{As2dPoint, Point};
+
+///Data aggregated over the elements of each node of a [`Quadtree`], computed bottom-up.
+///
+///`combine` should be associative and commutative, with `Default::default()` as identity,
+///so the summary of a node does not depend on how its elements are split between its children.
+pub trait Summary<T>: Default + Clone {
+    fn from_elem(elem: &T) -> Self;
+    fn combine(&self, other: &Self) -> Self;
+}
+
+///No summary.
+impl<T> Summary<T> for () {
+    #[inline(always)]
+    fn from_elem(_: &T) -> Self {}
+
+    #[inline(always)]
+    fn combine(&self, _: &Self) -> Self {}
+}
+
+///Summaries having a position, used to approximate far away nodes.
+pub trait Centroid<F: Float + Copy> {
+    ///`None` if the node can not be approximated, e.g. when it is empty.
+    fn centroid(&self) -> Option<Point<F>>;
+}
+
+pub trait Massive<F: Float + Copy>: As2dPoint<F> {
+    fn mass(&self) -> F;
+}
+
+///Total mass and center of mass of a node.
+#[derive(Debug, Clone, Copy)]
+pub struct MassSummary<F: Float + Copy> {
+    pub mass: F,
+    pub center_of_mass: Point<F>,
+}
+
+impl<F: Float + Copy> Default for MassSummary<F> {
+    fn default() -> Self {
+        Self {
+            mass: F::zero(),
+            center_of_mass: Point {
+                x: F::zero(),
+                y: F::zero(),
+            },
+        }
+    }
+}
+
+impl<F: Float + Copy, T: Massive<F>> Summary<T> for MassSummary<F> {
+    #[inline(always)]
+    fn from_elem(elem: &T) -> Self {
+        Self {
+            mass: elem.mass(),
+            center_of_mass: elem.as_point(),
+        }
+    }
+
+    fn combine(&self, other: &Self) -> Self {
+        let mass = self.mass + other.mass;
+        if mass == F::zero() {
+            return Self::default();
+        }
+        let (w_self, w_other) = (self.mass / mass, other.mass / mass);
+
+        Self {
+            mass,
+            center_of_mass: Point {
+                x: self.center_of_mass.x * w_self + other.center_of_mass.x * w_other,
+                y: self.center_of_mass.y * w_self + other.center_of_mass.y * w_other,
+            },
+        }
+    }
+}
+
+impl<F: Float + Copy> Centroid<F> for MassSummary<F> {
+    #[inline(always)]
+    fn centroid(&self) -> Option<Point<F>> {
+        (self.mass != F::zero()).then_some(self.center_of_mass)
+    }
+}
+
+impl<F, T, const N: usize, S> Quadtree<F, T, N, S>
+where
+    F: Float + Copy + Debug,
+    T: As2dPoint<F>,
+    S: Summary<T> + Centroid<F>,
+{
+    ///Barnes–Hut traversal around `point`.
+    ///
+    ///A node of size `s` (its biggest side) whose centroid is at a distance `d` of `point` is
+    ///approximated by its summary, given to `far`, when `s / d < theta` and `point` is out of it.
+    ///Otherwise its children are visited, and the elements of the leaves are given to `near`.
+    ///`near` may be given the element at `point` itself.
+    ///
+    ///`theta = 0` visits every element, usual values are around `0.5`.
+    pub fn barnes_hut<P: As2dPoint<F>>(
+        &self,
+        point: P,
+        theta: F,
+        mut far: impl FnMut(&S),
+        mut near: impl FnMut(&T),
+    ) {
+        let point = point.as_point();
+        let mut stack: Vec<&Node<F, N, S>> = vec![&self.base_node];
+
+        while let Some(curr_node) = stack.pop() {
+            match &curr_node.data {
+                NodeData::Leaf(leaf) => {
+                    for i_p in leaf.iter() {
+                        near(self.elem_at(i_p.i));
+                    }
+                }
+                NodeData::Child(child) => {
+                    let far_enough = curr_node.summary.centroid().is_some_and(|centroid| {
+                        let size = curr_node.boundary.width().max(curr_node.boundary.height());
+                        !curr_node.boundary.contain_pt(point) && size < theta * centroid.dist(point)
+                    });
+
+                    if far_enough {
+                        far(&curr_node.summary);
+                    } else {
+                        stack.extend(child.children());
+                    }
+                }
+            }
+        }
+    }
+}
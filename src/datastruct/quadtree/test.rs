@@ -1,6 +1,17 @@
 #![cfg(test)]
 
-use crate::datastruct::quadtree::{Aabb, As2dPoint, Quadtree};
+use std::cell::Cell;
+
+use super::NodeData;
+use crate::datastruct::{
+    points::Point,
+    quadtree::{
+        Aabb, ArenaQuadtree, As2dPoint, DEFAULT_MAX_DEPTH, MassSummary, Massive, Quadtree,
+        QueryMode, QueryStack, Summary, ValidationError,
+    },
+    shapes::Shape2d,
+    test_util::sorted_coords,
+};
 
 #[derive(Debug, Clone)]
 struct TestPoint {
@@ -56,6 +67,703 @@ fn test_rebuild() {
     }
 }
 
+fn spiral_points(nb: usize) -> Vec<TestPoint> {
+    (0..nb)
+        .map(|i| {
+            let t = i as f32 * 0.37;
+            TestPoint {
+                x: t.cos() * t,
+                y: t.sin() * t,
+            }
+        })
+        .collect()
+}
+
+fn brute_force_k_nearest(points: &[TestPoint], (x, y): (f32, f32), k: usize) -> Vec<f32> {
+    let mut dists: Vec<f32> = points
+        .iter()
+        .map(|p| ((p.x - x) * (p.x - x) + (p.y - y) * (p.y - y)).sqrt())
+        .collect();
+    dists.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    dists.truncate(k);
+    dists
+}
+
+#[test]
+fn test_nearest() {
+    let points = spiral_points(500);
+    let qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+
+    for query in [(0., 0.), (10., -3.), (-50., 42.), (150., 150.)] {
+        let (_, dist) = qtree.nearest(query).unwrap();
+        assert_eq!(dist, brute_force_k_nearest(&points, query, 1)[0]);
+    }
+
+    let empty: Quadtree<f32, TestPoint, 4> = Quadtree::empty(Aabb::new((0., 0.), 200.));
+    assert!(empty.nearest((0., 0.)).is_none());
+}
+
+#[test]
+fn test_k_nearest() {
+    let points = spiral_points(500);
+    let qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+
+    for query in [(0., 0.), (10., -3.), (-50., 42.)] {
+        let dists: Vec<f32> = qtree
+            .k_nearest(query, 20)
+            .into_iter()
+            .map(|(_, d)| d)
+            .collect();
+        assert_eq!(dists, brute_force_k_nearest(&points, query, 20));
+    }
+
+    assert_eq!(qtree.k_nearest((0., 0.), 1000).len(), 500);
+    assert!(qtree.k_nearest((0., 0.), 0).is_empty());
+}
+
+#[test]
+fn test_k_nearest_within() {
+    let points = spiral_points(500);
+    let qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+
+    let query = (12., 7.);
+    let result = qtree.k_nearest_within(query, 50, 5.);
+    let expected: Vec<f32> = brute_force_k_nearest(&points, query, 50)
+        .into_iter()
+        .filter(|d| *d <= 5.)
+        .collect();
+
+    assert!(!expected.is_empty());
+    assert_eq!(
+        result.into_iter().map(|(_, d)| d).collect::<Vec<_>>(),
+        expected
+    );
+    assert!(qtree.nearest_within((500., 500.), 1.).is_none());
+
+    //not the same as a radius of 5
+    assert!(qtree.k_nearest_within(query, 50, -5.).is_empty());
+    assert!(qtree.nearest_within(query, -5.).is_none());
+    assert!(qtree.nearest_within(query, f32::NAN).is_none());
+    assert_eq!(
+        qtree
+            .nearest_within((points[0].x, points[0].y), 0.)
+            .unwrap()
+            .1,
+        0.
+    );
+}
+
+#[test]
+fn test_remove() {
+    let points = spiral_points(200);
+    let mut qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+    let handles: Vec<_> = qtree.iter_with_handles().map(|(h, _)| h).collect();
+
+    let removed = qtree.remove(handles[10]).unwrap();
+    assert_eq!((removed.x, removed.y), (points[10].x, points[10].y));
+    assert_eq!(qtree.len(), 199);
+    assert!(qtree.remove(handles[10]).is_none());
+    assert!(qtree.get(handles[10]).is_none());
+
+    //other handles are still valid
+    let other = qtree.get(handles[199]).unwrap();
+    assert_eq!((other.x, other.y), (points[199].x, points[199].y));
+
+    let (nearest, dist) = qtree.nearest((points[10].x, points[10].y)).unwrap();
+    assert!(dist > 0.);
+    assert!((nearest.x, nearest.y) != (points[10].x, points[10].y));
+
+    for handle in handles {
+        qtree.remove(handle);
+    }
+    assert!(qtree.is_empty());
+    assert_eq!(qtree.depth(), 1);
+    assert!(qtree.query_range(Aabb::new((0., 0.), 200.)).is_empty());
+}
+
+#[test]
+fn test_remove_merge_nodes() {
+    let mut qtree: Quadtree<f32, TestPoint, 4> = Quadtree::empty(Aabb::new((0., 0.), 100.));
+    let handles: Vec<_> = spiral_points(5)
+        .into_iter()
+        .map(|p| qtree.insert(p).unwrap())
+        .collect();
+    assert!(qtree.depth() > 1);
+
+    qtree.remove(handles[4]);
+    assert_eq!(qtree.depth(), 1);
+    assert_eq!(qtree.query_range(Aabb::new((0., 0.), 100.)).len(), 4);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_coincident_points() {
+    let mut qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::empty(Aabb::new((0., 0.), 100.)).with_max_depth(8);
+    let handles: Vec<_> = (0..5000)
+        .map(|_| qtree.insert(TestPoint { x: 3., y: -7. }).unwrap())
+        .collect();
+    assert_eq!(qtree.depth(), 8);
+    assert_eq!(qtree.len(), 5000);
+
+    assert_eq!(qtree.query_range(Aabb::new((3., -7.), 0.1)).len(), 5000);
+    assert_eq!(qtree.k_nearest((0., 0.), 10).len(), 10);
+    assert!(qtree.query_range(Aabb::new((-50., 50.), 10.)).is_empty());
+
+    for handle in &handles[..4990] {
+        assert!(qtree.remove(*handle).is_some());
+    }
+    assert_eq!(qtree.query_range(Aabb::new((3., -7.), 0.1)).len(), 10);
+    for handle in &handles[4990..] {
+        assert!(qtree.remove(*handle).is_some());
+    }
+    assert_eq!(qtree.depth(), 1);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_coincident_points_default_depth() {
+    let points = vec![(1., 1.); 3000];
+    let mut qtree: Quadtree<f64, _, 8> = Quadtree::new(Aabb::new((0., 0.), 10.), points);
+    assert!(qtree.depth() <= DEFAULT_MAX_DEPTH);
+    assert_eq!(qtree.query_circle((1., 1.), 0.).len(), 3000);
+
+    qtree.rebuild().unwrap();
+    assert_eq!(qtree.query_range(Aabb::new((1., 1.), 1.)).len(), 3000);
+}
+
+#[test]
+fn test_quadrant_edge_points() {
+    let mut qtree: Quadtree<f32, TestPoint, 2> =
+        Quadtree::empty(Aabb::new((0., 0.), 64.)).with_max_depth(6);
+    let mut nb = 0;
+    //the centers and the edges of the nodes, repeated
+    for _ in 0..20 {
+        for x in [-64., -32., -16., 0., 16., 32., 64.] {
+            for y in [-64., -32., 0., 8., 32., 64.] {
+                qtree.insert(TestPoint { x, y }).unwrap();
+                nb += 1;
+            }
+        }
+    }
+    assert_eq!(qtree.len(), nb);
+    assert!(qtree.depth() <= 6);
+    assert_eq!(qtree.query_range(Aabb::new((0., 0.), 64.)).len(), nb);
+    assert_eq!(qtree.query_circle((0., 0.), 0.).len(), 20);
+    assert_eq!(
+        qtree
+            .query_range(Aabb::from_min_max((0., 0.), (32., 32.)))
+            .len(),
+        20 * 3 * 3
+    );
+}
+
+///subdivisions and merges back and forth, small enough to run under Miri
+#[test]
+fn test_subdivide_merge_cycle() {
+    let mut qtree: Quadtree<f32, TestPoint, 2> =
+        Quadtree::empty(Aabb::new((0., 0.), 16.)).with_max_depth(4);
+    let points = spiral_points(12);
+
+    for round in 0..3 {
+        let handles: Vec<_> = points
+            .iter()
+            .map(|p| qtree.insert(p.clone()).unwrap())
+            .collect();
+        assert!(qtree.depth() > 1);
+
+        let copy = qtree.clone();
+        for handle in handles.iter().rev() {
+            qtree.get_mut(*handle).unwrap().x *= -1.;
+            qtree.update_position(*handle).unwrap();
+        }
+        assert_eq!(copy.len(), qtree.len());
+        drop(copy);
+
+        for (k, handle) in handles.into_iter().enumerate() {
+            assert!(qtree.remove(handle).is_some());
+            assert_eq!(qtree.len(), points.len() - k - 1);
+        }
+        assert_eq!(qtree.depth(), 1, "round {round}");
+    }
+}
+
+#[test]
+fn test_handle_reuse() {
+    let mut qtree: Quadtree<f32, TestPoint, 4> = Quadtree::empty(Aabb::new((0., 0.), 100.));
+    let first = qtree.insert(TestPoint { x: 1., y: 1. }).unwrap();
+    qtree.remove(first);
+
+    let second = qtree.insert(TestPoint { x: 2., y: 2. }).unwrap();
+    assert_ne!(first, second);
+    assert!(qtree.get(first).is_none());
+    assert_eq!(qtree.get(second).unwrap().x, 2.);
+    assert_eq!(qtree.query_range(Aabb::new((0., 0.), 100.)).len(), 1);
+}
+
+#[test]
+fn test_update_position() {
+    let mut qtree: Quadtree<f32, TestPoint, 4> = Quadtree::empty(Aabb::new((0., 0.), 200.));
+    let handles: Vec<_> = spiral_points(100)
+        .into_iter()
+        .map(|p| qtree.insert(p).unwrap())
+        .collect();
+
+    let elem = qtree.get_mut(handles[42]).unwrap();
+    elem.x = 150.;
+    elem.y = -150.;
+    qtree.update_position(handles[42]).unwrap();
+
+    let found = qtree.query_range(Aabb::new((150., -150.), 1.));
+    assert_eq!(found.len(), 1);
+    assert_eq!((found[0].x, found[0].y), (150., -150.));
+    assert_eq!(qtree.nearest((150., -150.)).unwrap().1, 0.);
+
+    qtree.get_mut(handles[42]).unwrap().x = 1000.;
+    assert!(qtree.update_position(handles[42]).is_err());
+    assert_eq!(qtree.query_range(Aabb::new((150., -150.), 1.)).len(), 1);
+
+    qtree.remove(handles[42]);
+    assert!(qtree.update_position(handles[42]).is_err());
+}
+
+#[test]
+fn test_query_circle() {
+    let points = spiral_points(500);
+    let qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+
+    let (center, radius) = ((-20., 15.), 30.);
+    let expected = sorted_coords(
+        points
+            .iter()
+            .filter(|p| (p.x - center.0).powi(2) + (p.y - center.1).powi(2) <= radius * radius),
+    );
+
+    assert!(!expected.is_empty());
+    assert_eq!(sorted_coords(qtree.query_circle(center, radius)), expected);
+}
+
+#[test]
+fn test_query_rect() {
+    let points = spiral_points(500);
+    let qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+
+    let expected = sorted_coords(
+        points
+            .iter()
+            .filter(|p| -100. <= p.x && p.x <= 100. && -5. <= p.y && p.y <= 10.),
+    );
+
+    assert!(!expected.is_empty());
+    assert_eq!(
+        sorted_coords(qtree.query_rect((-100., -5.), (100., 10.))),
+        expected
+    );
+}
+
+#[test]
+fn test_query_shape() {
+    struct HalfPlane;
+    impl Shape2d<f32> for HalfPlane {
+        fn intersects_aabb(&self, aabb: Aabb<f32>) -> bool {
+            aabb.max().x >= 0.
+        }
+        fn contains_point(&self, point: Point<f32>) -> bool {
+            point.x >= 0.
+        }
+    }
+
+    let points = spiral_points(500);
+    let qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+
+    let expected = sorted_coords(points.iter().filter(|p| p.x >= 0.));
+    assert_eq!(sorted_coords(qtree.query_shape(&HalfPlane)), expected);
+}
+
+#[test]
+fn test_query_range_iter() {
+    let points = spiral_points(500);
+    let qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+
+    let mut stack = QueryStack::new();
+    for range in [
+        Aabb::new((0., 0.), 10.),
+        Aabb::new((-50., 30.), 25.),
+        Aabb::new((300., 300.), 5.),
+    ] {
+        let expected = sorted_coords(points.iter().filter(|p| range.contain_pt(p.as_point())));
+        assert_eq!(sorted_coords(qtree.query_range_iter(range)), expected);
+
+        let mut iter = qtree.query_range_iter_with(range, stack);
+        let found = sorted_coords(iter.by_ref());
+        stack = iter.into_stack();
+        assert_eq!(found, expected);
+    }
+}
+
+#[test]
+fn test_query_range_into() {
+    let points = spiral_points(500);
+    let qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+    let brute_force = |range: Aabb<f32>| {
+        points
+            .iter()
+            .filter(move |p| range.contain_pt(p.as_point()))
+    };
+
+    let first = Aabb::new((0., 0.), 10.);
+    let second = Aabb::new((-50., 30.), 25.);
+
+    let mut result = vec![];
+    qtree.query_range_into(first, &mut result);
+    assert_eq!(
+        sorted_coords(result.clone()),
+        sorted_coords(brute_force(first))
+    );
+
+    //appended after the first results
+    qtree.query_range_into(second, &mut result);
+    assert_eq!(
+        sorted_coords(result),
+        sorted_coords(brute_force(first).chain(brute_force(second)))
+    );
+}
+
+#[test]
+fn test_query_range_exact() {
+    let points = spiral_points(500);
+    let mut qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+    assert_eq!(qtree.query_mode(), QueryMode::Exact);
+
+    for range in [
+        Aabb::new((0., 0.), 10.),
+        Aabb::new((-50., 30.), 25.),
+        Aabb::new((13., -7.), 3.5),
+    ] {
+        let expected = sorted_coords(points.iter().filter(|p| range.contain_pt(p.as_point())));
+        assert_eq!(sorted_coords(qtree.query_range(range)), expected);
+        assert_eq!(sorted_coords(qtree.query_range_iter(range)), expected);
+
+        let count = Cell::new(0);
+        qtree.map_query_range(range, |_| count.set(count.get() + 1));
+        assert_eq!(count.get(), expected.len());
+    }
+}
+
+#[test]
+fn test_query_range_broad_phase() {
+    let points = spiral_points(500);
+    let qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone())
+            .with_query_mode(QueryMode::BroadPhase);
+
+    let range = Aabb::new((13., -7.), 3.5);
+    let exact = points
+        .iter()
+        .filter(|p| range.contain_pt(p.as_point()))
+        .count();
+    let broad = qtree.query_range(range);
+
+    //the whole leaves are returned
+    assert!(broad.len() > exact);
+    assert_eq!(
+        broad
+            .iter()
+            .filter(|p| range.contain_pt(p.as_point()))
+            .count(),
+        exact
+    );
+    assert_eq!(qtree.query_range_iter(range).count(), broad.len());
+}
+
+#[test]
+fn test_rect_boundary() {
+    let boundary = Aabb::from_min_max((0., 0.), (1920., 1080.));
+    let mut qtree: Quadtree<f32, TestPoint, 4> = Quadtree::empty(boundary);
+
+    assert!(qtree.insert(TestPoint { x: 1900., y: 10. }).is_ok());
+    assert!(qtree.insert(TestPoint { x: 10., y: 1070. }).is_ok());
+    assert!(qtree.insert(TestPoint { x: 10., y: 1900. }).is_err());
+
+    let points: Vec<_> = (0..400)
+        .map(|i| TestPoint {
+            x: (i % 40) as f32 * 48. + 1.,
+            y: (i / 40) as f32 * 108. + 1.,
+        })
+        .collect();
+    for p in points.clone() {
+        qtree.insert(p).unwrap();
+    }
+
+    let range = Aabb::from_min_max((100., 100.), (1000., 300.));
+    let expected = sorted_coords(points.iter().filter(|p| range.contain_pt(p.as_point())));
+    assert!(!expected.is_empty());
+    assert_eq!(sorted_coords(qtree.query_range(range)), expected);
+}
+
+#[test]
+fn test_rebuild_fit_rect() {
+    let points: Vec<_> = (0..100)
+        .map(|i| TestPoint {
+            x: i as f32 * 100.,
+            y: (i % 3) as f32,
+        })
+        .collect();
+    let qtree: Quadtree<f32, TestPoint, 4> = Quadtree::new(Aabb::new((0., 0.), 1.), points.clone());
+
+    assert_eq!(qtree.len(), 100);
+    assert!(qtree.depth() < 10);
+    assert_eq!(qtree.query_rect((-1., -1.), (10_000., 3.)).len(), 100);
+
+    //the given boundary grows to contain the elements, then by a margin of 1
+    let boundary = Aabb::new((0., 0.), 10.);
+    let qtree: Quadtree<f32, TestPoint, 4> = Quadtree::new(boundary, points);
+    assert!(qtree.base_node.boundary.contain_aabb(boundary));
+    let (min, max) = (
+        qtree.base_node.boundary.min(),
+        qtree.base_node.boundary.max(),
+    );
+    assert_eq!((min.x, min.y, max.x, max.y), (-11., -11., 9901., 11.));
+}
+
+#[derive(Debug, Default)]
+struct Force {
+    x: f32,
+    y: f32,
+}
+
+fn repulsion(force: &mut Force, p: &TestPoint, other: &TestPoint) {
+    let (dx, dy) = (p.x - other.x, p.y - other.y);
+    let dist_sq = (dx * dx + dy * dy).max(0.01);
+    force.x += dx / dist_sq;
+    force.y += dy / dist_sq;
+}
+
+fn apply_force(p: &mut TestPoint, force: Force) {
+    p.x += force.x * 0.1;
+    p.y += force.y * 0.1;
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_accumulate_with_elem_in_range() {
+    let points = spiral_points(300);
+    let mut qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+    let range_mapping = |p: &TestPoint| Aabb::new((p.x, p.y), 10.);
+
+    qtree.accumulate_with_elem_in_range(range_mapping, repulsion, apply_force);
+
+    //brute force, every point sees the others before they move
+    let expected: Vec<(f32, f32)> = points
+        .iter()
+        .map(|p| {
+            let mut force = Force::default();
+            for other in &points {
+                if !std::ptr::eq(p, other) && range_mapping(p).contain_pt(other.as_point()) {
+                    repulsion(&mut force, p, other);
+                }
+            }
+            let mut moved = p.clone();
+            apply_force(&mut moved, force);
+            (moved.x, moved.y)
+        })
+        .collect();
+
+    for ((_, p), (x, y)) in qtree.iter_with_handles().zip(expected) {
+        assert!((p.x - x).abs() < 1e-4 && (p.y - y).abs() < 1e-4);
+    }
+
+    //the tree follows the moved points
+    let (x, y) = (
+        qtree.iter().next().unwrap().x,
+        qtree.iter().next().unwrap().y,
+    );
+    assert_eq!(qtree.nearest((x, y)).unwrap().1, 0.);
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_par_accumulate_with_elem_in_range() {
+    let mut points = spiral_points(2000);
+    for p in &mut points {
+        p.x *= 0.3;
+    }
+    let mut seq_qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points);
+    let handles: Vec<_> = seq_qtree.iter_with_handles().map(|(h, _)| h).collect();
+    seq_qtree.remove(handles[17]);
+    let mut par_qtree = seq_qtree.clone();
+
+    let range_mapping = |p: &TestPoint| Aabb::new((p.x, p.y), 8.);
+    for _ in 0..3 {
+        seq_qtree.accumulate_with_elem_in_range(range_mapping, repulsion, apply_force);
+        par_qtree.par_accumulate_with_elem_in_range(range_mapping, repulsion, apply_force);
+    }
+
+    let seq: Vec<_> = seq_qtree.iter().map(|p| (p.x, p.y)).collect();
+    let par: Vec<_> = par_qtree.iter().map(|p| (p.x, p.y)).collect();
+    assert_eq!(seq, par);
+    assert_eq!(par_qtree.len(), 1999);
+}
+
+#[derive(Debug, Clone, Default)]
+struct Count(usize);
+
+impl<T> Summary<T> for Count {
+    fn from_elem(_: &T) -> Self {
+        Count(1)
+    }
+    fn combine(&self, other: &Self) -> Self {
+        Count(self.0 + other.0)
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_summary_updates() {
+    let mut qtree: Quadtree<f32, TestPoint, 4, Count> = Quadtree::empty(Aabb::new((0., 0.), 200.));
+    let handles: Vec<_> = spiral_points(300)
+        .into_iter()
+        .map(|p| qtree.insert(p).unwrap())
+        .collect();
+    assert_eq!(qtree.summary().0, 300);
+
+    for h in &handles[..100] {
+        qtree.remove(*h);
+    }
+    assert_eq!(qtree.summary().0, 200);
+
+    qtree.get_mut(handles[150]).unwrap().x = -190.;
+    qtree.update_position(handles[150]).unwrap();
+    assert_eq!(qtree.summary().0, 200);
+
+    qtree.insert_fit(TestPoint { x: 1000., y: 0. });
+    assert_eq!(qtree.summary().0, 201);
+
+    let qtree: Quadtree<f32, TestPoint, 4, Count> =
+        Quadtree::new(Aabb::new((0., 0.), 1.), spiral_points(50));
+    assert_eq!(qtree.summary().0, 50);
+}
+
+#[derive(Debug, Clone)]
+struct Body {
+    x: f32,
+    y: f32,
+    mass: f32,
+}
+
+impl As2dPoint<f32> for Body {
+    fn x(&self) -> f32 {
+        self.x
+    }
+    fn y(&self) -> f32 {
+        self.y
+    }
+}
+
+impl Massive<f32> for Body {
+    fn mass(&self) -> f32 {
+        self.mass
+    }
+}
+
+fn bodies(nb: usize) -> Vec<Body> {
+    spiral_points(nb)
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| Body {
+            x: p.x,
+            y: p.y,
+            mass: 1. + (i % 5) as f32,
+        })
+        .collect()
+}
+
+fn gravity(on: (f32, f32), from: Point<f32>, mass: f32) -> (f32, f32) {
+    let (dx, dy) = (from.x - on.0, from.y - on.1);
+    let dist_sq = dx * dx + dy * dy;
+    if dist_sq == 0. {
+        return (0., 0.);
+    }
+    let f = mass / (dist_sq * dist_sq.sqrt());
+    (dx * f, dy * f)
+}
+
+#[test]
+fn test_mass_summary() {
+    let bodies = bodies(400);
+    let qtree: Quadtree<f32, Body, 4, MassSummary<f32>> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), bodies.clone());
+
+    let total_mass: f32 = bodies.iter().map(|b| b.mass).sum();
+    let center_x = bodies.iter().map(|b| b.x * b.mass).sum::<f32>() / total_mass;
+    let center_y = bodies.iter().map(|b| b.y * b.mass).sum::<f32>() / total_mass;
+
+    let summary = qtree.summary();
+    assert!((summary.mass - total_mass).abs() < 1e-2);
+    assert!((summary.center_of_mass.x - center_x).abs() < 1e-2);
+    assert!((summary.center_of_mass.y - center_y).abs() < 1e-2);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_barnes_hut() {
+    let bodies = bodies(1000);
+    let qtree: Quadtree<f32, Body, 4, MassSummary<f32>> =
+        Quadtree::new(Aabb::new((0., 0.), 400.), bodies.clone());
+
+    let on = (-250., 180.);
+    let exact = bodies.iter().fold((0., 0.), |acc, b| {
+        let f = gravity(on, b.as_point(), b.mass);
+        (acc.0 + f.0, acc.1 + f.1)
+    });
+
+    let barnes_hut = |theta: f32| {
+        let force = Cell::new((0., 0.));
+        let add = |f: (f32, f32)| {
+            let (x, y) = force.get();
+            force.set((x + f.0, y + f.1));
+        };
+        let far_count = Cell::new(0);
+        qtree.barnes_hut(
+            on,
+            theta,
+            |s| {
+                far_count.set(far_count.get() + 1);
+                add(gravity(on, s.center_of_mass, s.mass))
+            },
+            |b| add(gravity(on, b.as_point(), b.mass)),
+        );
+        (force.get(), far_count.get())
+    };
+
+    let error = |force: (f32, f32)| {
+        ((force.0 - exact.0).powi(2) + (force.1 - exact.1).powi(2)).sqrt()
+            / (exact.0 * exact.0 + exact.1 * exact.1).sqrt()
+    };
+
+    //no approximation
+    let (force, far_count) = barnes_hut(0.);
+    assert_eq!(far_count, 0);
+    assert!(error(force) < 1e-4);
+
+    let (force, far_count) = barnes_hut(0.5);
+    assert!(far_count > 0);
+    assert!(error(force) < 2e-2);
+}
+
 #[test]
 #[ignore]
 fn test_lot_of_insert() {
@@ -156,3 +864,491 @@ fn test_lot_of_map_then_map_with_elem_in_range_then_map() {
         time_pass
     );
 }
+
+fn brute_force_ray(
+    points: &[(f64, f64)],
+    origin: (f64, f64),
+    dir: (f64, f64),
+    max_dist: f64,
+    radius: f64,
+) -> Vec<f64> {
+    let len = (dir.0 * dir.0 + dir.1 * dir.1).sqrt();
+    let dir = (dir.0 / len, dir.1 / len);
+    let mut hits: Vec<_> = points
+        .iter()
+        .filter_map(|&p| {
+            let t = ((p.0 - origin.0) * dir.0 + (p.1 - origin.1) * dir.1).clamp(0., max_dist);
+            let closest = (origin.0 + dir.0 * t, origin.1 + dir.1 * t);
+            let dist_sq = (p.0 - closest.0).powi(2) + (p.1 - closest.1).powi(2);
+            (dist_sq <= radius * radius).then_some(t)
+        })
+        .collect();
+    hits.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    hits
+}
+
+#[test]
+fn test_raycast() {
+    let points: Vec<(f64, f64)> = spiral_points(500)
+        .iter()
+        .map(|p| (p.x as f64, p.y as f64))
+        .collect();
+    let tree: Quadtree<_, _, 4> = Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+
+    for (origin, dir, max_dist, radius) in [
+        ((0., 0.), (1., 0.), 100., 2.),
+        ((-150., -120.), (1., 1.), 400., 5.),
+        ((30., -200.), (0., 1.), 150., 0.5),
+        ((10., 10.), (-3., 1.), 1000., 10.),
+    ] {
+        let hits = tree.raycast(origin, dir, max_dist, radius);
+        let expected = brute_force_ray(&points, origin, dir, max_dist, radius);
+        assert!(!expected.is_empty());
+        assert_eq!(hits.len(), expected.len());
+        for ((_, t), expected_t) in hits.iter().zip(&expected) {
+            assert!((t - expected_t).abs() < 1e-9);
+        }
+
+        let (_, first_t) = tree.raycast_first(origin, dir, max_dist, radius).unwrap();
+        assert!((first_t - expected[0]).abs() < 1e-9);
+    }
+
+    assert!(tree.raycast((0., 0.), (0., 0.), 100., 2.).is_empty());
+    assert!(
+        tree.raycast_first((500., 500.), (1., 0.), 100., 2.)
+            .is_none()
+    );
+}
+
+#[test]
+fn test_query_segment() {
+    let points = vec![(1., 0.), (2., 0.5), (5., -0.5), (3., 3.), (-1., 0.)];
+    let tree: Quadtree<_, _, 2> = Quadtree::new(Aabb::new((0., 0.), 10.), points);
+
+    let hits = tree.query_segment((0., 0.), (4., 0.), 1.);
+    let hits: Vec<_> = hits.into_iter().map(|(p, t)| (*p, t)).collect();
+    assert_eq!(hits, vec![((-1., 0.), 0.), ((1., 0.), 1.), ((2., 0.5), 2.)]);
+
+    //the hit on the segment end is at the segment length
+    let hits = tree.query_segment((0., 0.), (4., 0.), 1.2);
+    assert_eq!(hits.last().map(|(p, t)| (**p, *t)), Some(((5., -0.5), 4.)));
+}
+
+#[test]
+fn test_arena_matches_boxed() {
+    let points = spiral_points(500);
+    let boxed: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+    let arena: ArenaQuadtree<f32, TestPoint, 4> =
+        ArenaQuadtree::new(Aabb::new((0., 0.), 200.), points.clone());
+    assert_eq!(arena.len(), 500);
+    assert_eq!(arena.depth(), boxed.depth());
+
+    for range in [
+        Aabb::new((0., 0.), 30.),
+        Aabb::new_rect((-100., 50.), 60., 20.),
+        Aabb::new((150., -150.), 80.),
+    ] {
+        assert_eq!(
+            sorted_coords(arena.query_range(range)),
+            sorted_coords(boxed.query_range(range))
+        );
+    }
+    assert_eq!(
+        sorted_coords(arena.query_circle((20., -40.), 45.)),
+        sorted_coords(boxed.query_circle((20., -40.), 45.))
+    );
+
+    let dists: Vec<_> = arena
+        .k_nearest((13., 27.), 20)
+        .into_iter()
+        .map(|(_, d)| d)
+        .collect();
+    let expected = brute_force_k_nearest(&points, (13., 27.), 20);
+    for (d, e) in dists.iter().zip(&expected) {
+        assert!((d - e).abs() < 1e-4);
+    }
+    assert_eq!(arena.nearest((13., 27.)).unwrap().1, dists[0]);
+}
+
+#[test]
+fn test_arena_insert_remove() {
+    let mut arena: ArenaQuadtree<f32, TestPoint, 4> =
+        ArenaQuadtree::empty(Aabb::new((0., 0.), 200.));
+    let points = spiral_points(200);
+    let handles: Vec<_> = points
+        .iter()
+        .map(|p| arena.insert(p.clone()).unwrap())
+        .collect();
+    assert!(arena.depth() > 1);
+    assert!(arena.insert(TestPoint { x: 500., y: 0. }).is_err());
+
+    let node_count = arena.node_count();
+    for handle in &handles[..150] {
+        assert!(arena.remove(*handle).is_some());
+        assert!(arena.remove(*handle).is_none());
+    }
+    assert_eq!(arena.query_range(Aabb::new((0., 0.), 200.)).len(), 50);
+    assert!(arena.node_count() < node_count);
+
+    //the freed blocks are reused
+    for p in &points[..150] {
+        arena.insert(p.clone()).unwrap();
+    }
+    assert_eq!(arena.node_count(), node_count);
+
+    let handles: Vec<_> = arena.iter_with_handles().map(|(h, _)| h).collect();
+    for handle in handles {
+        arena.remove(handle);
+    }
+    assert_eq!(arena.depth(), 1);
+    assert_eq!(arena.node_count(), 1);
+}
+
+#[test]
+fn test_arena_update_position() {
+    let mut arena: ArenaQuadtree<f32, TestPoint, 4> =
+        ArenaQuadtree::empty(Aabb::new((0., 0.), 200.));
+    let handles: Vec<_> = spiral_points(100)
+        .into_iter()
+        .map(|p| arena.insert(p).unwrap())
+        .collect();
+
+    for handle in &handles {
+        let p = arena.get_mut(*handle).unwrap();
+        p.x = -p.x;
+        arena.update_position(*handle).unwrap();
+    }
+    let mirrored: Vec<_> = spiral_points(100)
+        .into_iter()
+        .map(|p| TestPoint { x: -p.x, y: p.y })
+        .collect();
+    let range = Aabb::new((-20., 10.), 40.);
+    let expected: Vec<_> = mirrored
+        .iter()
+        .filter(|p| range.contain_pt(p.as_point()))
+        .collect();
+    assert_eq!(
+        sorted_coords(arena.query_range(range)),
+        sorted_coords(expected)
+    );
+
+    arena.get_mut(handles[0]).unwrap().x = 1000.;
+    assert!(arena.update_position(handles[0]).is_err());
+
+    //removed from where the tree stored it, not from where the element went
+    let (x, y) = (-mirrored[1].x, mirrored[1].y);
+    let p = arena.get_mut(handles[1]).unwrap();
+    (p.x, p.y) = (x, y);
+    arena.remove(handles[1]).unwrap();
+    assert_eq!(arena.len(), 99);
+    assert!(
+        arena
+            .query_range(Aabb::new((0., 0.), 200.))
+            .iter()
+            .all(|p| (p.x, p.y) != (x, y) && (p.x, p.y) != (mirrored[1].x, mirrored[1].y))
+    );
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_arena_coincident_points() {
+    let mut arena: ArenaQuadtree<f32, TestPoint, 4> =
+        ArenaQuadtree::empty(Aabb::new((0., 0.), 100.)).with_max_depth(6);
+    for _ in 0..2000 {
+        arena.insert(TestPoint { x: -3., y: 5. }).unwrap();
+    }
+    assert_eq!(arena.depth(), 6);
+    assert_eq!(arena.query_circle((-3., 5.), 0.).len(), 2000);
+
+    arena.rebuild().unwrap();
+    assert_eq!(arena.depth(), 6);
+    assert_eq!(arena.query_circle((-3., 5.), 0.).len(), 2000);
+}
+
+fn incremental<const N: usize>(
+    boundary: Aabb<f32>,
+    points: &[TestPoint],
+) -> Quadtree<f32, TestPoint, N> {
+    let mut qtree = Quadtree::empty(boundary);
+    for p in points {
+        qtree.insert(p.clone()).unwrap();
+    }
+    qtree
+}
+
+#[test]
+fn test_from_slice() {
+    let mut points = spiral_points(1000);
+    //on the node edges
+    points.extend((-8..=8).map(|k| TestPoint {
+        x: k as f32 * 25.,
+        y: 0.,
+    }));
+    points.extend((-8..=8).map(|k| TestPoint {
+        x: 50.,
+        y: k as f32 * 12.5,
+    }));
+    let boundary = Aabb::new((0., 0.), 400.);
+
+    let bulk: Quadtree<f32, TestPoint, 4> = Quadtree::from_slice(boundary, &points);
+    let inserted = incremental::<4>(boundary, &points);
+    assert_eq!(bulk.len(), points.len());
+    assert_eq!(bulk.depth(), inserted.depth());
+
+    for range in [
+        Aabb::new((0., 0.), 50.),
+        Aabb::new((50., 0.), 12.5),
+        Aabb::new_rect((-200., 100.), 100., 20.),
+        Aabb::new((0., 0.), 400.),
+    ] {
+        assert_eq!(
+            sorted_coords(bulk.query_range(range)),
+            sorted_coords(inserted.query_range(range))
+        );
+    }
+    assert_eq!(
+        bulk.k_nearest((10., 10.), 15)
+            .into_iter()
+            .map(|(_, d)| d)
+            .collect::<Vec<_>>(),
+        inserted
+            .k_nearest((10., 10.), 15)
+            .into_iter()
+            .map(|(_, d)| d)
+            .collect::<Vec<_>>()
+    );
+
+    //the handles follow the slice and the points are found again
+    let mut bulk = bulk;
+    let handles: Vec<_> = bulk.iter_with_handles().map(|(h, _)| h).collect();
+    for (handle, p) in handles.iter().zip(&points) {
+        assert_eq!(bulk.get(*handle).unwrap().x, p.x);
+    }
+    for handle in handles {
+        assert!(bulk.remove(handle).is_some());
+    }
+    assert_eq!(bulk.depth(), 1);
+}
+
+#[test]
+fn test_from_slice_deeper_than_codes() {
+    let points = vec![(1., 2.); 50];
+    let boundary = Aabb::new((0., 0.), 10.);
+
+    let bulk: Quadtree<f64, _, 4> = Quadtree::from_slice(boundary, &points);
+    assert_eq!(bulk.depth(), DEFAULT_MAX_DEPTH);
+
+    let mut bulk = bulk.with_max_depth(40);
+    bulk.rebuild_fit();
+    assert_eq!(bulk.depth(), 40);
+    assert_eq!(bulk.query_circle((1., 2.), 0.).len(), 50);
+
+    let mut inserted: Quadtree<f64, _, 4> = Quadtree::empty(boundary).with_max_depth(40);
+    for p in points {
+        inserted.insert(p).unwrap();
+    }
+    assert_eq!(inserted.depth(), 40);
+}
+
+#[test]
+fn test_node_boundaries() {
+    let mut qtree: Quadtree<f32, TestPoint, 1> = Quadtree::empty(Aabb::new((0., 0.), 8.));
+    qtree.insert(TestPoint { x: 1., y: 1. }).unwrap();
+    let as_tuple =
+        |(aabb, depth): (Aabb<f32>, usize)| (aabb.center.x, aabb.center.y, aabb.half_width, depth);
+    let boundaries: Vec<_> = qtree.node_boundaries().into_iter().map(as_tuple).collect();
+    assert_eq!(boundaries, vec![(0., 0., 8., 1)]);
+    assert_eq!(qtree.debug_line_vertices().len(), 8);
+
+    qtree.insert(TestPoint { x: -1., y: 1. }).unwrap();
+    let boundaries: Vec<_> = qtree.node_boundaries().into_iter().map(as_tuple).collect();
+    assert_eq!(boundaries.len(), 5);
+    assert_eq!(boundaries[0], (0., 0., 8., 1));
+    assert!(
+        boundaries[1..]
+            .iter()
+            .all(|(x, y, half_width, depth)| *depth == 2
+                && *half_width == 4.
+                && x.abs() == 4.
+                && y.abs() == 4.)
+    );
+
+    let vertices = qtree.debug_line_vertices();
+    assert_eq!(vertices.len(), 5 * 8);
+    assert_eq!(vertices[0].position, [-8., -8., 0., 1.]);
+    assert_eq!(vertices[1].position, [8., -8., 0., 1.]);
+}
+
+#[test]
+fn test_to_svg() {
+    let qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), spiral_points(100));
+    let svg = qtree.to_svg();
+
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert!(svg.contains(r#"viewBox="-200 -200 400 400""#));
+    assert_eq!(svg.matches("<rect").count(), qtree.node_boundaries().len());
+    assert_eq!(svg.matches("<circle").count(), 100);
+
+    //a color per depth
+    for depth in 1..=qtree.depth() {
+        assert!(svg.contains(&format!("hsl({}, 80%, 45%)", ((depth - 1) * 47) % 360)));
+    }
+    //y points up: the root top left corner is at (-200, -200)
+    assert!(svg.contains(r#"<rect x="-200" y="-200" width="400" height="400""#));
+}
+
+#[test]
+fn test_stats() {
+    let mut qtree: Quadtree<f32, TestPoint, 2> = Quadtree::empty(Aabb::new((0., 0.), 8.));
+    let stats = qtree.stats();
+    assert_eq!(stats.node_count, 1);
+    assert_eq!(stats.occupancy, vec![1, 0, 0]);
+
+    for (x, y) in [(1., 1.), (2., 2.), (-1., 1.)] {
+        qtree.insert(TestPoint { x, y }).unwrap();
+    }
+    let stats = qtree.stats();
+    assert_eq!(stats.node_count, 5);
+    assert_eq!(stats.leaf_count, 4);
+    assert_eq!(stats.empty_leaves, 2);
+    assert_eq!(stats.overflowing_leaves, 0);
+    assert_eq!(stats.occupancy, vec![2, 1, 1]);
+    assert_eq!(stats.nodes_per_depth, vec![1, 4]);
+
+    qtree.set_max_depth(2);
+    for _ in 0..3 {
+        qtree.insert(TestPoint { x: -1., y: 1. }).unwrap();
+    }
+    let stats = qtree.stats();
+    assert_eq!(stats.nodes_per_depth, vec![1, 4]);
+    assert_eq!(stats.overflowing_leaves, 1);
+    assert_eq!(stats.occupancy, vec![2, 0, 2]);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_validate_after_mutations() {
+    let mut qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 200.), spiral_points(300)).with_max_depth(6);
+    qtree.validate().unwrap();
+
+    let mut handles: Vec<_> = qtree.iter_with_handles().map(|(h, _)| h).collect();
+    for round in 0..5 {
+        for (k, handle) in handles.iter().enumerate() {
+            let p = qtree.get_mut(*handle).unwrap();
+            p.x = (p.x * 0.7 + k as f32 * 0.1).clamp(-199., 199.);
+            p.y = if k % 7 == 0 {
+                0.
+            } else {
+                (p.y + 3.).clamp(-199., 199.)
+            };
+            qtree.update_position(*handle).unwrap();
+        }
+        qtree.validate().unwrap();
+
+        for handle in handles.drain(..handles.len() / 3) {
+            qtree.remove(handle);
+        }
+        for k in 0..50 {
+            let t = (round * 50 + k) as f32;
+            handles.push(
+                qtree
+                    .insert(TestPoint {
+                        x: (t * 1.3).sin() * 150.,
+                        y: if k % 5 == 0 {
+                            25.
+                        } else {
+                            (t * 0.7).cos() * 150.
+                        },
+                    })
+                    .unwrap(),
+            );
+        }
+        qtree.validate().unwrap();
+    }
+
+    qtree.rebuild().unwrap();
+    qtree.validate().unwrap();
+}
+
+#[test]
+fn test_validate_errors() {
+    let mut qtree: Quadtree<f32, TestPoint, 4> =
+        Quadtree::new(Aabb::new((0., 0.), 10.), spiral_points(3));
+    qtree.validate().unwrap();
+
+    let NodeData::Leaf(leaf) = &mut qtree.base_node.data else {
+        unreachable!()
+    };
+    let first = leaf.points[0];
+    leaf.points.push(first);
+    assert!(matches!(
+        qtree.validate(),
+        Err(ValidationError::DuplicateIndex(i)) if i == first.i
+    ));
+
+    let NodeData::Leaf(leaf) = &mut qtree.base_node.data else {
+        unreachable!()
+    };
+    leaf.points.pop();
+    leaf.points[0].x = 50.;
+    assert!(matches!(
+        qtree.validate(),
+        Err(ValidationError::PointOutOfNode { index, .. }) if index == first.i
+    ));
+
+    let NodeData::Leaf(leaf) = &mut qtree.base_node.data else {
+        unreachable!()
+    };
+    leaf.points.remove(0);
+    assert!(matches!(
+        qtree.validate(),
+        Err(ValidationError::MissingIndex(i)) if i == first.i
+    ));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_quadtree_serde() {
+    let points: Vec<(f64, f64)> = spiral_points(200)
+        .iter()
+        .map(|p| (p.x as f64, p.y as f64))
+        .collect();
+    let mut qtree: Quadtree<f64, (f64, f64), 4, Count> =
+        Quadtree::new(Aabb::new((0., 0.), 100.), points)
+            .with_query_mode(QueryMode::BroadPhase)
+            .with_max_depth(7);
+    let handles: Vec<_> = qtree.iter_with_handles().map(|(h, _)| h).collect();
+    for handle in &handles[..50] {
+        qtree.remove(*handle);
+    }
+
+    let json = serde_json::to_string(&qtree).unwrap();
+    let back: Quadtree<f64, (f64, f64), 4, Count> = serde_json::from_str(&json).unwrap();
+    back.validate().unwrap();
+    assert_eq!(back.len(), 150);
+    assert_eq!(back.query_mode(), QueryMode::BroadPhase);
+    assert_eq!(back.max_depth(), 7);
+    assert_eq!(back.summary().0, 150);
+    assert_eq!(back.depth(), qtree.depth());
+
+    //the handles stay valid
+    assert!(handles[..50].iter().all(|h| !back.contains(*h)));
+    for handle in &handles[50..] {
+        assert_eq!(back.get(*handle), qtree.get(*handle));
+    }
+    let range = Aabb::new((10., -20.), 30.);
+    let mut expected: Vec<_> = qtree.query_range(range).into_iter().copied().collect();
+    let mut result: Vec<_> = back.query_range(range).into_iter().copied().collect();
+    expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    result.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(result, expected);
+
+    //an element out of the boundary is rejected
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    value["boundary"]["half_width"] = 1.into();
+    assert!(serde_json::from_value::<Quadtree<f64, (f64, f64), 4>>(value).is_err());
+}
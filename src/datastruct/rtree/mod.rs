@@ -0,0 +1,724 @@
+#[cfg(test)]
+mod test;
+
+use std::{cmp::Ordering, fmt::Debug, mem};
+
+use num::Float;
+
+use super::{
+    aabb::{Aabb, Bounded2d},
+    error::SpatialError2d,
+    points::{As2dPoint, Point},
+    slotmap::{Handle, SlotMap},
+};
+
+///R-tree storing objects with a size, without a boundary.
+///
+///Each node holds at most `N` entries, and its box is the smallest one containing them.
+///The insertion follows the R*-tree: the subtree growing the overlap the least is chosen,
+///an overflowing node first reinserts its farthest entries and is split along the axis with the
+///smallest margins otherwise. The non-root nodes built by insertion hold at least `2N / 5`
+///entries, and removals keep it so. `new` packs the elements with Sort-Tile-Recursive, where the
+///last node of a level can hold fewer.
+///Elements are tested with the corners of their bounds, the same way as the nodes.
+#[derive(Debug, Clone)]
+pub struct RTree<F: Float + Copy + Debug, T: Bounded2d<F>, const N: usize> {
+    elems: SlotMap<RElem<F, T>>,
+    root: RNode<F>,
+    ///level of the root, the leaves being at level 0
+    height: usize,
+}
+
+#[derive(Debug, Clone)]
+struct RElem<F: Float + Copy + Debug, T> {
+    elem: T,
+    ///the bounds the element has in the tree
+    aabb: Aabb<F>,
+}
+
+///corners of a box, empty when `min > max`
+#[derive(Debug, Clone, Copy)]
+struct Rect<F: Float + Copy + Debug> {
+    min: Point<F>,
+    max: Point<F>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct REntry<F: Float + Copy + Debug> {
+    rect: Rect<F>,
+    i: usize,
+}
+
+#[derive(Debug, Clone)]
+struct RNode<F: Float + Copy + Debug> {
+    rect: Rect<F>,
+    children: RChildren<F>,
+}
+
+#[derive(Debug, Clone)]
+enum RChildren<F: Float + Copy + Debug> {
+    Leaf(Vec<REntry<F>>),
+    Inner(Vec<RNode<F>>),
+}
+
+///entry or subtree waiting to be put in a node of a given level
+enum Item<F: Float + Copy + Debug> {
+    Entry(REntry<F>),
+    Node(RNode<F>),
+}
+
+struct InsertCtx<F: Float + Copy + Debug> {
+    max: usize,
+    min: usize,
+    ///levels where entries were already reinserted, an overflow there splits the node
+    reinserted: Vec<bool>,
+    pending: Vec<(Item<F>, usize)>,
+}
+
+trait HasRect<F: Float + Copy + Debug> {
+    fn rect(&self) -> Rect<F>;
+}
+
+#[allow(dead_code)]
+impl<F: Float + Copy + Debug, T: Bounded2d<F>, const N: usize> RTree<F, T, N> {
+    const MIN: usize = if N * 2 / 5 > 1 { N * 2 / 5 } else { 1 };
+
+    pub fn empty() -> Self {
+        debug_assert!(N >= 4, "The size should be a least 4");
+
+        Self {
+            elems: SlotMap::new(),
+            root: RNode::leaf(vec![]),
+            height: 0,
+        }
+    }
+
+    ///Packs the elements with Sort-Tile-Recursive, which gives fuller nodes and less overlap
+    ///than inserting them one by one.
+    ///Fails if the bounds of an element have invalid coordinates.
+    pub fn new(vec: Vec<T>) -> Result<Self, SpatialError2d<F>> {
+        let mut tree = Self::empty();
+        let mut entries = Vec::with_capacity(vec.len());
+        for elem in vec {
+            let aabb = elem.aabb();
+            let rect = Rect::of(aabb)?;
+            let handle = tree.elems.insert(RElem { elem, aabb });
+            entries.push(REntry {
+                rect,
+                i: handle.index(),
+            });
+        }
+
+        if entries.len() <= N {
+            tree.root = RNode::leaf(entries);
+            return Ok(tree);
+        }
+        let mut nodes: Vec<_> = str_pack(entries, N).into_iter().map(RNode::leaf).collect();
+        while nodes.len() > N {
+            nodes = str_pack(nodes, N).into_iter().map(RNode::inner).collect();
+            tree.height += 1;
+        }
+        tree.root = RNode::inner(nodes);
+        tree.height += 1;
+        Ok(tree)
+    }
+
+    ///See `new`.
+    pub fn from_slice(slice: &[T]) -> Result<Self, SpatialError2d<F>>
+    where
+        T: Clone,
+    {
+        Self::new(slice.to_vec())
+    }
+
+    pub fn len(&self) -> usize {
+        self.elems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///Number of levels, the leaves included.
+    pub fn depth(&self) -> usize {
+        self.height + 1
+    }
+
+    ///Smallest box containing all the elements, `None` if there is none.
+    pub fn bounds(&self) -> Option<Aabb<F>> {
+        (!self.is_empty()).then(|| self.root.rect.to_aabb())
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.elems.get(handle).map(|r_e| &r_e.elem)
+    }
+
+    ///After changing the bounds of the element, call `update` to move it in the tree.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.elems.get_mut(handle).map(|r_e| &mut r_e.elem)
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.elems.contains(handle)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.elems.values().map(|r_e| &r_e.elem)
+    }
+
+    pub fn iter_with_handles(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.elems.iter().map(|(h, r_e)| (h, &r_e.elem))
+    }
+
+    pub fn insert(&mut self, elem: T) -> Result<Handle, SpatialError2d<F>> {
+        let aabb = elem.aabb();
+        let rect = Rect::of(aabb)?;
+
+        let handle = self.elems.insert(RElem { elem, aabb });
+        self.insert_item(
+            Item::Entry(REntry {
+                rect,
+                i: handle.index(),
+            }),
+            0,
+        );
+        Ok(handle)
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let r_e = self.elems.remove(handle)?;
+        let removed = self.remove_entry(REntry {
+            rect: Rect::of(r_e.aabb).ok()?,
+            i: handle.index(),
+        });
+        debug_assert!(
+            removed,
+            "something went wrong in RTree::remove: the element is not in the tree"
+        );
+
+        Some(r_e.elem)
+    }
+
+    ///Moves the element of `handle` in the tree according to its current bounds.
+    ///On error, the element keeps its previous place in the tree.
+    pub fn update(&mut self, handle: Handle) -> Result<(), SpatialError2d<F>> {
+        let r_e = self
+            .elems
+            .get(handle)
+            .ok_or(SpatialError2d::InvalidHandle(handle))?;
+        let (old_aabb, new_aabb) = (r_e.aabb, r_e.elem.aabb());
+        let (old_rect, new_rect) = (Rect::of(old_aabb)?, Rect::of(new_aabb)?);
+        if old_rect.contains(new_rect) && new_rect.contains(old_rect) {
+            return Ok(());
+        }
+
+        self.remove_entry(REntry {
+            rect: old_rect,
+            i: handle.index(),
+        });
+        self.insert_item(
+            Item::Entry(REntry {
+                rect: new_rect,
+                i: handle.index(),
+            }),
+            0,
+        );
+        if let Some(r_e) = self.elems.get_mut(handle) {
+            r_e.aabb = new_aabb;
+        }
+        Ok(())
+    }
+
+    ///Elements whose bounds intersect `range`, edges included.
+    pub fn query_overlap(&self, range: Aabb<F>) -> Vec<&T> {
+        self.handles_to_elems(self.query_overlap_handles(range))
+    }
+
+    pub fn query_overlap_handles(&self, range: Aabb<F>) -> Vec<Handle> {
+        let Ok(range) = Rect::of(range) else {
+            return vec![];
+        };
+        self.collect_handles(|rect| rect.intersects(range), |rect| rect.intersects(range))
+    }
+
+    ///Elements whose bounds are inside `range`, edges included.
+    pub fn query_contained(&self, range: Aabb<F>) -> Vec<&T> {
+        let Ok(range) = Rect::of(range) else {
+            return vec![];
+        };
+        self.handles_to_elems(
+            self.collect_handles(|rect| rect.intersects(range), |rect| range.contains(rect)),
+        )
+    }
+
+    ///Elements whose bounds contain `point`, edges included.
+    ///Replaces testing every element, when looking for what is under the cursor.
+    pub fn query_point<P: As2dPoint<F>>(&self, point: P) -> Vec<&T> {
+        self.handles_to_elems(self.query_point_handles(point))
+    }
+
+    pub fn query_point_handles<P: As2dPoint<F>>(&self, point: P) -> Vec<Handle> {
+        let point = point.as_point();
+        let rect = Rect {
+            min: point,
+            max: point,
+        };
+        self.collect_handles(|r| r.contains(rect), |r| r.contains(rect))
+    }
+
+    fn handles_to_elems(&self, handles: Vec<Handle>) -> Vec<&T> {
+        handles.into_iter().filter_map(|h| self.get(h)).collect()
+    }
+
+    ///handles of the entries matching `keep_entry`, in the nodes matching `visit_node`
+    fn collect_handles(
+        &self,
+        visit_node: impl Fn(Rect<F>) -> bool,
+        keep_entry: impl Fn(Rect<F>) -> bool,
+    ) -> Vec<Handle> {
+        let mut result = vec![];
+        let mut stack = vec![&self.root];
+
+        while let Some(node) = stack.pop() {
+            if !visit_node(node.rect) {
+                continue;
+            }
+            match &node.children {
+                RChildren::Leaf(entries) => result.extend(
+                    entries
+                        .iter()
+                        .filter(|entry| keep_entry(entry.rect))
+                        .filter_map(|entry| self.elems.handle_at(entry.i)),
+                ),
+                RChildren::Inner(children) => stack.extend(children),
+            }
+        }
+        result
+    }
+
+    ///puts `item` in a node of `level`, then the entries it pushed out
+    fn insert_item(&mut self, item: Item<F>, level: usize) {
+        let mut ctx = InsertCtx {
+            max: N,
+            min: Self::MIN,
+            reinserted: vec![false; self.height + 1],
+            pending: vec![(item, level)],
+        };
+
+        while let Some((item, level)) = ctx.pending.pop() {
+            //the tree shrank since the subtree was taken out of it
+            if level > self.height {
+                if let Item::Node(node) = item {
+                    ctx.pending
+                        .extend(node.into_items().into_iter().map(|it| (it, level - 1)));
+                }
+                continue;
+            }
+
+            let height = self.height;
+            if let Some(sibling) = self.root.insert(item, level, height, true, &mut ctx) {
+                let old_root = mem::replace(&mut self.root, RNode::leaf(vec![]));
+                self.root = RNode::inner(vec![old_root, sibling]);
+                self.height += 1;
+                ctx.reinserted.push(false);
+            }
+        }
+    }
+
+    ///removes the entry, then reinserts the entries of the nodes left with too few of them
+    fn remove_entry(&mut self, entry: REntry<F>) -> bool {
+        let mut orphans = vec![];
+        let height = self.height;
+        if !self.root.remove(entry, height, Self::MIN, &mut orphans) {
+            return false;
+        }
+
+        loop {
+            match &mut self.root.children {
+                RChildren::Inner(children) if children.len() == 1 => {
+                    self.root = children.pop().unwrap();
+                    self.height -= 1;
+                }
+                RChildren::Inner(children) if children.is_empty() => {
+                    self.root = RNode::leaf(vec![]);
+                    self.height = 0;
+                }
+                _ => break,
+            }
+        }
+        for (item, level) in orphans {
+            self.insert_item(item, level);
+        }
+        true
+    }
+}
+
+impl<F: Float + Copy + Debug> Rect<F> {
+    fn of(aabb: Aabb<F>) -> Result<Self, SpatialError2d<F>> {
+        let (min, max) = (aabb.min(), aabb.max());
+        for corner in [min, max] {
+            if !corner.as_valid_coord() {
+                return Err(SpatialError2d::InvalidCoord((corner.x, corner.y)));
+            }
+        }
+        Ok(Self { min, max })
+    }
+
+    fn empty() -> Self {
+        let (inf, neg_inf) = (F::infinity(), F::neg_infinity());
+        Self {
+            min: Point { x: inf, y: inf },
+            max: Point {
+                x: neg_inf,
+                y: neg_inf,
+            },
+        }
+    }
+
+    fn to_aabb(self) -> Aabb<F> {
+        Aabb::from_min_max(self.min, self.max)
+    }
+
+    fn union(self, other: Self) -> Self {
+        Self {
+            min: Point {
+                x: self.min.x.min(other.min.x),
+                y: self.min.y.min(other.min.y),
+            },
+            max: Point {
+                x: self.max.x.max(other.max.x),
+                y: self.max.y.max(other.max.y),
+            },
+        }
+    }
+
+    fn area(self) -> F {
+        (self.max.x - self.min.x).max(F::zero()) * (self.max.y - self.min.y).max(F::zero())
+    }
+
+    ///half perimeter
+    fn margin(self) -> F {
+        (self.max.x - self.min.x).max(F::zero()) + (self.max.y - self.min.y).max(F::zero())
+    }
+
+    fn overlap(self, other: Self) -> F {
+        let w = self.max.x.min(other.max.x) - self.min.x.max(other.min.x);
+        let h = self.max.y.min(other.max.y) - self.min.y.max(other.min.y);
+        w.max(F::zero()) * h.max(F::zero())
+    }
+
+    fn intersects(self, other: Self) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+
+    fn contains(self, other: Self) -> bool {
+        self.min.x <= other.min.x
+            && self.min.y <= other.min.y
+            && other.max.x <= self.max.x
+            && other.max.y <= self.max.y
+    }
+
+    ///squared distance between the centers, doubled on each axis
+    fn center_dist_sq(self, other: Self) -> F {
+        let dx = (self.min.x + self.max.x) - (other.min.x + other.max.x);
+        let dy = (self.min.y + self.max.y) - (other.min.y + other.max.y);
+        dx * dx + dy * dy
+    }
+
+    fn bound<E: HasRect<F>>(items: &[E]) -> Self {
+        items
+            .iter()
+            .fold(Self::empty(), |rect, item| rect.union(item.rect()))
+    }
+}
+
+impl<F: Float + Copy + Debug> HasRect<F> for REntry<F> {
+    fn rect(&self) -> Rect<F> {
+        self.rect
+    }
+}
+
+impl<F: Float + Copy + Debug> HasRect<F> for RNode<F> {
+    fn rect(&self) -> Rect<F> {
+        self.rect
+    }
+}
+
+impl<F: Float + Copy + Debug> Item<F> {
+    fn rect(&self) -> Rect<F> {
+        match self {
+            Item::Entry(entry) => entry.rect,
+            Item::Node(node) => node.rect,
+        }
+    }
+}
+
+impl<F: Float + Copy + Debug> RNode<F> {
+    fn leaf(entries: Vec<REntry<F>>) -> Self {
+        Self {
+            rect: Rect::bound(&entries),
+            children: RChildren::Leaf(entries),
+        }
+    }
+
+    fn inner(children: Vec<RNode<F>>) -> Self {
+        Self {
+            rect: Rect::bound(&children),
+            children: RChildren::Inner(children),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match &self.children {
+            RChildren::Leaf(entries) => entries.len(),
+            RChildren::Inner(children) => children.len(),
+        }
+    }
+
+    fn update_rect(&mut self) {
+        self.rect = match &self.children {
+            RChildren::Leaf(entries) => Rect::bound(entries),
+            RChildren::Inner(children) => Rect::bound(children),
+        };
+    }
+
+    fn into_items(self) -> Vec<Item<F>> {
+        match self.children {
+            RChildren::Leaf(entries) => entries.into_iter().map(Item::Entry).collect(),
+            RChildren::Inner(children) => children.into_iter().map(Item::Node).collect(),
+        }
+    }
+
+    ///puts `item` in the node of level `target` below this one, of level `level`.
+    ///Returns the new sibling of this node if it was split.
+    fn insert(
+        &mut self,
+        item: Item<F>,
+        target: usize,
+        level: usize,
+        is_root: bool,
+        ctx: &mut InsertCtx<F>,
+    ) -> Option<RNode<F>> {
+        if level == target {
+            match (&mut self.children, item) {
+                (RChildren::Leaf(entries), Item::Entry(entry)) => entries.push(entry),
+                (RChildren::Inner(children), Item::Node(node)) => children.push(node),
+                _ => unreachable!("an entry can only go in a leaf, and a subtree in an inner node"),
+            }
+        } else {
+            let RChildren::Inner(children) = &mut self.children else {
+                unreachable!("a leaf is at level 0")
+            };
+            let i = choose_subtree(children, item.rect(), level == 1);
+            if let Some(sibling) = children[i].insert(item, target, level - 1, false, ctx) {
+                children.push(sibling);
+            }
+        }
+        self.update_rect();
+
+        if self.len() <= ctx.max {
+            return None;
+        }
+        if !is_root && !ctx.reinserted[level] {
+            ctx.reinserted[level] = true;
+            let center = self.rect;
+            let count = (ctx.max * 3 / 10).max(1);
+            let removed = match &mut self.children {
+                RChildren::Leaf(entries) => take_farthest(entries, center, count)
+                    .into_iter()
+                    .map(Item::Entry)
+                    .collect::<Vec<_>>(),
+                RChildren::Inner(children) => take_farthest(children, center, count)
+                    .into_iter()
+                    .map(Item::Node)
+                    .collect(),
+            };
+            self.update_rect();
+            //the closest one is reinserted first
+            ctx.pending
+                .extend(removed.into_iter().map(|item| (item, level)));
+            return None;
+        }
+
+        let sibling = match &mut self.children {
+            RChildren::Leaf(entries) => RNode::leaf(split(entries, ctx.min)),
+            RChildren::Inner(children) => RNode::inner(split(children, ctx.min)),
+        };
+        self.update_rect();
+        Some(sibling)
+    }
+
+    ///removes the entry from the subtree of level `level`, the nodes below it left with less
+    ///than `min` children are taken out and their children put in `orphans` with their level
+    fn remove(
+        &mut self,
+        entry: REntry<F>,
+        level: usize,
+        min: usize,
+        orphans: &mut Vec<(Item<F>, usize)>,
+    ) -> bool {
+        match &mut self.children {
+            RChildren::Leaf(entries) => {
+                let Some(pos) = entries.iter().position(|e| e.i == entry.i) else {
+                    return false;
+                };
+                entries.swap_remove(pos);
+            }
+            RChildren::Inner(children) => {
+                let Some(pos) = children.iter_mut().position(|child| {
+                    child.rect.contains(entry.rect) && child.remove(entry, level - 1, min, orphans)
+                }) else {
+                    return false;
+                };
+                if children[pos].len() < min {
+                    let child = children.swap_remove(pos);
+                    orphans.extend(child.into_items().into_iter().map(|it| (it, level - 1)));
+                }
+            }
+        }
+        self.update_rect();
+        true
+    }
+}
+
+///child whose box grows the least by adding `rect`: in overlap with the other children when they
+///are leaves, in area otherwise. Ties are broken by the smallest area.
+fn choose_subtree<F: Float + Copy + Debug>(
+    children: &[RNode<F>],
+    rect: Rect<F>,
+    leaf_children: bool,
+) -> usize {
+    let cost = |i: usize| {
+        let old = children[i].rect;
+        let new = old.union(rect);
+        let overlap_growth = if leaf_children {
+            children
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .fold(F::zero(), |acc, (_, other)| {
+                    acc + new.overlap(other.rect) - old.overlap(other.rect)
+                })
+        } else {
+            F::zero()
+        };
+        (overlap_growth, new.area() - old.area(), old.area())
+    };
+
+    (0..children.len())
+        .map(|i| (i, cost(i)))
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+        .map_or(0, |(i, _)| i)
+}
+
+///removes the `count` items whose centers are the farthest from the one of `rect`,
+///the farthest first
+fn take_farthest<F: Float + Copy + Debug, E: HasRect<F>>(
+    items: &mut Vec<E>,
+    rect: Rect<F>,
+    count: usize,
+) -> Vec<E> {
+    items.sort_by(|a, b| {
+        rect.center_dist_sq(b.rect())
+            .partial_cmp(&rect.center_dist_sq(a.rect()))
+            .unwrap_or(Ordering::Equal)
+    });
+    let rest = items.split_off(count);
+    mem::replace(items, rest)
+}
+
+///R* split: the axis is the one with the smallest sum of margins over all the distributions,
+///the distribution is the one with the smallest overlap, then area.
+///Keeps the first group in `items` and returns the second.
+fn split<F: Float + Copy + Debug, E: HasRect<F>>(items: &mut Vec<E>, min: usize) -> Vec<E> {
+    type Key<F> = fn(Rect<F>) -> F;
+    let sorts: [[Key<F>; 2]; 2] = [[|r| r.min.x, |r| r.max.x], [|r| r.min.y, |r| r.max.y]];
+    let sort_by = |items: &mut Vec<E>, key: Key<F>| {
+        items.sort_by(|a, b| {
+            key(a.rect())
+                .partial_cmp(&key(b.rect()))
+                .unwrap_or(Ordering::Equal)
+        })
+    };
+    //boxes of the first `k` items and of the others, for every valid `k`
+    let distributions = |items: &[E]| {
+        let n = items.len();
+        (min..=n - min)
+            .map(|k| (k, Rect::bound(&items[..k]), Rect::bound(&items[k..])))
+            .collect::<Vec<_>>()
+    };
+
+    let axis = (0..2)
+        .map(|axis| {
+            let mut margins = F::zero();
+            for key in sorts[axis] {
+                sort_by(items, key);
+                for (_, a, b) in distributions(items) {
+                    margins = margins + a.margin() + b.margin();
+                }
+            }
+            (axis, margins)
+        })
+        .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+        .map_or(0, |(axis, _)| axis);
+
+    let mut best: Option<(F, F, Key<F>, usize)> = None;
+    for key in sorts[axis] {
+        sort_by(items, key);
+        for (k, a, b) in distributions(items) {
+            let cost = (a.overlap(b), a.area() + b.area());
+            if best.is_none_or(|(overlap, area, _, _)| cost < (overlap, area)) {
+                best = Some((cost.0, cost.1, key, k));
+            }
+        }
+    }
+    let (_, _, key, k) = best.expect("a node overflows with at least 2 * min + 1 items");
+    sort_by(items, key);
+    items.split_off(k)
+}
+
+///groups of at most `max` items close to each other: the items are sorted by x in vertical
+///slices, each slice being sorted by y and cut in groups
+fn str_pack<F: Float + Copy + Debug, E: HasRect<F>>(mut items: Vec<E>, max: usize) -> Vec<Vec<E>> {
+    let center = |item: &E, axis: usize| {
+        let rect = item.rect();
+        if axis == 0 {
+            rect.min.x + rect.max.x
+        } else {
+            rect.min.y + rect.max.y
+        }
+    };
+    let sort_along = |items: &mut Vec<E>, axis: usize| {
+        items.sort_by(|a, b| {
+            center(a, axis)
+                .partial_cmp(&center(b, axis))
+                .unwrap_or(Ordering::Equal)
+        })
+    };
+
+    let groups = items.len().div_ceil(max);
+    let slices = (groups as f64).sqrt().ceil() as usize;
+    sort_along(&mut items, 0);
+
+    let mut result = Vec::with_capacity(groups);
+    for mut slice in even_chunks(items, slices) {
+        sort_along(&mut slice, 1);
+        let count = slice.len().div_ceil(max);
+        result.extend(even_chunks(slice, count));
+    }
+    result
+}
+
+///`items` cut in `count` consecutive chunks whose lengths differ by at most one
+fn even_chunks<E>(mut items: Vec<E>, count: usize) -> Vec<Vec<E>> {
+    let mut chunks = Vec::with_capacity(count);
+    for remaining in (1..=count).rev() {
+        let len = items.len() / remaining;
+        let rest = items.split_off(len);
+        chunks.push(mem::replace(&mut items, rest));
+    }
+    chunks
+}
@@ -0,0 +1,219 @@
+#![cfg(test)]
+
+use crate::datastruct::{
+    aabb::{Aabb, Bounded2d},
+    points::As2dPoint,
+    rtree::{RChildren, RNode, RTree},
+    slotmap::Handle,
+    test_util::Rng,
+};
+
+#[derive(Debug, Clone)]
+struct Widget {
+    id: usize,
+    aabb: Aabb<f64>,
+}
+
+impl Bounded2d<f64> for Widget {
+    fn aabb(&self) -> Aabb<f64> {
+        self.aabb
+    }
+}
+
+///rectangles on a grid of quarters, so that every corner is exact
+fn random_aabb(rng: &mut Rng) -> Aabb<f64> {
+    let quarter = |v: f64| (v * 4.).round() / 4.;
+    let center = (
+        quarter(rng.next_f64() * 200. - 100.),
+        quarter(rng.next_f64() * 200. - 100.),
+    );
+    //mostly small rectangles, with a few big ones
+    let scale = if rng.next_f64() < 0.05 { 40. } else { 5. };
+    let half_width = quarter(rng.next_f64() * scale) + 0.25;
+    let half_height = quarter(rng.next_f64() * scale) + 0.25;
+    Aabb::new_rect(center, half_width, half_height)
+}
+
+fn widgets(nb: usize, seed: u64) -> Vec<Widget> {
+    let mut rng = Rng::new(seed);
+    (0..nb)
+        .map(|id| Widget {
+            id,
+            aabb: random_aabb(&mut rng),
+        })
+        .collect()
+}
+
+fn sorted_ids(elems: Vec<&Widget>) -> Vec<usize> {
+    let mut ids: Vec<_> = elems.into_iter().map(|w| w.id).collect();
+    ids.sort();
+    ids
+}
+
+///every leaf is at the same level, the boxes are the smallest ones containing the children
+///and the nodes are not too full, nor too empty when `check_min`. Returns the number of entries.
+fn check_node<const N: usize>(
+    node: &RNode<f64>,
+    level: usize,
+    is_root: bool,
+    check_min: bool,
+) -> usize {
+    let min = (N * 2 / 5).max(1);
+    assert!(node.len() <= N);
+    if !is_root && check_min {
+        assert!(node.len() >= min);
+    }
+
+    let (count, rects): (usize, Vec<_>) = match &node.children {
+        RChildren::Leaf(entries) => {
+            assert_eq!(level, 0);
+            (entries.len(), entries.iter().map(|e| e.rect).collect())
+        }
+        RChildren::Inner(children) => {
+            assert!(level > 0);
+            let count = children
+                .iter()
+                .map(|child| check_node::<N>(child, level - 1, false, check_min))
+                .sum();
+            (count, children.iter().map(|c| c.rect).collect())
+        }
+    };
+    if count > 0 {
+        let bound = rects.into_iter().reduce(|a, b| a.union(b)).unwrap();
+        assert!(bound.contains(node.rect) && node.rect.contains(bound));
+    }
+    count
+}
+
+fn check_tree<const N: usize>(tree: &RTree<f64, Widget, N>, check_min: bool) {
+    assert_eq!(
+        check_node::<N>(&tree.root, tree.height, true, check_min),
+        tree.len()
+    );
+}
+
+fn check_queries<const N: usize>(tree: &RTree<f64, Widget, N>, reference: &[&Widget], seed: u64) {
+    let mut rng = Rng::new(seed);
+    for _ in 0..50 {
+        let range = random_aabb(&mut rng);
+        let range = range.expand(rng.next_f64().round() * 10.);
+        assert_eq!(
+            sorted_ids(tree.query_overlap(range)),
+            sorted_ids(
+                reference
+                    .iter()
+                    .copied()
+                    .filter(|w| w.aabb.intersect(range))
+                    .collect()
+            )
+        );
+        assert_eq!(
+            sorted_ids(tree.query_contained(range)),
+            sorted_ids(
+                reference
+                    .iter()
+                    .copied()
+                    .filter(|w| range.contain_aabb(w.aabb))
+                    .collect()
+            )
+        );
+        let point = (range.center.x, range.min().y);
+        assert_eq!(
+            sorted_ids(tree.query_point(point)),
+            sorted_ids(
+                reference
+                    .iter()
+                    .copied()
+                    .filter(|w| w.aabb.contain_pt(point.as_point()))
+                    .collect()
+            )
+        );
+    }
+}
+
+#[test]
+fn test_rtree_bulk_load() {
+    for nb in [0, 3, 8, 9, 100, 2000] {
+        let elems = widgets(nb, 42 + nb as u64);
+        let tree: RTree<f64, Widget, 8> = RTree::from_slice(&elems).unwrap();
+        assert_eq!(tree.len(), nb);
+        check_tree(&tree, true);
+        check_queries(&tree, &elems.iter().collect::<Vec<_>>(), 7);
+    }
+
+    let tree: RTree<f64, Widget, 8> = RTree::new(widgets(2000, 1)).unwrap();
+    assert_eq!(tree.depth(), 4);
+    let bounds = tree.bounds().unwrap();
+    assert!(tree.iter().all(|w| bounds.contain_aabb(w.aabb)));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_rtree_insert_remove() {
+    let elems = widgets(1500, 3);
+    let mut tree: RTree<f64, Widget, 6> = RTree::empty();
+    let mut handles: Vec<(Handle, usize)> = elems
+        .iter()
+        .map(|w| (tree.insert(w.clone()).unwrap(), w.id))
+        .collect();
+    check_tree(&tree, true);
+    check_queries(&tree, &elems.iter().collect::<Vec<_>>(), 11);
+
+    //removes two thirds of the elements, in a scattered order
+    let mut rng = Rng::new(5);
+    for _ in 0..1000 {
+        let i = rng.below(handles.len());
+        let (handle, id) = handles.swap_remove(i);
+        assert_eq!(tree.remove(handle).unwrap().id, id);
+        assert!(!tree.contains(handle));
+        assert!(tree.remove(handle).is_none());
+    }
+    assert_eq!(tree.len(), 500);
+    check_tree(&tree, true);
+    let reference: Vec<_> = handles.iter().map(|&(_, id)| &elems[id]).collect();
+    check_queries(&tree, &reference, 13);
+
+    for (handle, _) in handles {
+        tree.remove(handle).unwrap();
+    }
+    assert!(tree.is_empty());
+    assert_eq!(tree.depth(), 1);
+    assert!(tree.bounds().is_none());
+    assert!(tree.query_overlap(Aabb::new((0., 0.), 1000.)).is_empty());
+}
+
+#[test]
+fn test_rtree_update() {
+    let mut elems = widgets(300, 9);
+    let mut tree: RTree<f64, Widget, 8> = RTree::new(elems.clone()).unwrap();
+    let handles: Vec<_> = tree.iter_with_handles().map(|(h, w)| (h, w.id)).collect();
+
+    let mut rng = Rng::new(17);
+    for &(handle, id) in handles.iter().step_by(3) {
+        let aabb = random_aabb(&mut rng);
+        tree.get_mut(handle).unwrap().aabb = aabb;
+        tree.update(handle).unwrap();
+        elems[id].aabb = aabb;
+    }
+    check_tree(&tree, false);
+    check_queries(&tree, &elems.iter().collect::<Vec<_>>(), 19);
+
+    let (handle, _) = handles[1];
+    tree.get_mut(handle).unwrap().aabb = Aabb::new((f64::NAN, 0.), 1.);
+    assert!(tree.update(handle).is_err());
+    //the element keeps its previous place
+    let center = elems[handles[1].1].aabb.center;
+    assert!(
+        tree.query_point_handles((center.x, center.y))
+            .contains(&handle)
+    );
+    tree.remove(handle).unwrap();
+    assert!(tree.update(handle).is_err());
+    assert!(
+        tree.insert(Widget {
+            id: 0,
+            aabb: Aabb::new((0., f64::INFINITY), 1.)
+        })
+        .is_err()
+    );
+}
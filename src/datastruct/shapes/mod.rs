@@ -0,0 +1,56 @@
+#[cfg(test)]
+mod test;
+
+use num::Float;
+
+use super::{
+    aabb::Aabb,
+    points::{As2dPoint, Point},
+};
+
+///A region of the plane that can be used to query spatial structures.
+pub trait Shape2d<F: Float + Copy> {
+    ///true if the shape may overlap the box, used to prune whole nodes
+    fn intersects_aabb(&self, aabb: Aabb<F>) -> bool;
+    fn contains_point(&self, point: Point<F>) -> bool;
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Circle<F: Float + Copy> {
+    pub center: Point<F>,
+    pub radius: F,
+}
+
+impl<F: Float + Copy> Circle<F> {
+    pub fn new<P: As2dPoint<F>>(center: P, radius: F) -> Self {
+        debug_assert!(radius >= F::zero(), "radius should always be >= 0.");
+        Self {
+            center: center.as_point(),
+            radius,
+        }
+    }
+}
+
+impl<F: Float + Copy> Shape2d<F> for Circle<F> {
+    #[inline(always)]
+    fn intersects_aabb(&self, aabb: Aabb<F>) -> bool {
+        aabb.dist_sq_to_pt(self.center) <= self.radius * self.radius
+    }
+
+    #[inline(always)]
+    fn contains_point(&self, point: Point<F>) -> bool {
+        self.center.dist_sq(point) <= self.radius * self.radius
+    }
+}
+
+impl<F: Float + Copy> Shape2d<F> for Aabb<F> {
+    #[inline(always)]
+    fn intersects_aabb(&self, aabb: Aabb<F>) -> bool {
+        self.intersect(aabb)
+    }
+
+    #[inline(always)]
+    fn contains_point(&self, point: Point<F>) -> bool {
+        self.contain_pt(point)
+    }
+}
@@ -0,0 +1,32 @@
+#![cfg(test)]
+
+use crate::datastruct::{
+    aabb::Aabb,
+    points::As2dPoint,
+    shapes::{Circle, Shape2d},
+};
+
+#[test]
+fn test_circle() {
+    let circle = Circle::new((0., 0.), 2.);
+
+    assert!(circle.contains_point((1., 1.).as_point()));
+    assert!(circle.contains_point((2., 0.).as_point()));
+    assert!(!circle.contains_point((1.5, 1.5).as_point()));
+
+    assert!(circle.intersects_aabb(Aabb::new((3., 0.), 1.)));
+    assert!(circle.intersects_aabb(Aabb::new((0., 0.), 10.)));
+    //the corner of the box is out of the circle
+    assert!(!circle.intersects_aabb(Aabb::new((2.5, 2.5), 1.)));
+}
+
+#[test]
+fn test_aabb_shape() {
+    let aabb = Aabb::from_min_max((0., 0.), (4., 1.));
+
+    assert!(aabb.contains_point((3.5, 0.5).as_point()));
+    assert!(!aabb.contains_point((2., 2.).as_point()));
+
+    assert!(aabb.intersects_aabb(Aabb::new((2., 2.), 1.)));
+    assert!(!aabb.intersects_aabb(Aabb::new((2., 3.), 1.)));
+}
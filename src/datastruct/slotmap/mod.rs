@@ -0,0 +1,244 @@
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(test)]
+mod test;
+
+///Stable reference to an element of a [`SlotMap`].
+///A handle stays valid until its element is removed, even if other elements are removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Handle {
+    index: usize,
+    generation: u32,
+}
+
+impl Handle {
+    #[inline(always)]
+    pub(crate) fn index(self) -> usize {
+        self.index
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+}
+
+///Vec backed storage, giving out generational [`Handle`]s.
+///Removed slots are reused, and the generation of a slot is bumped on each removal
+///so old handles on this slot become invalid.
+#[derive(Debug, Clone)]
+pub struct SlotMap<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<usize>,
+    len: usize,
+}
+
+impl<T> Default for SlotMap<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<Vec<T>> for SlotMap<T> {
+    ///The element `i` of the vec get the slot `i`.
+    fn from(vec: Vec<T>) -> Self {
+        let len = vec.len();
+        Self {
+            slots: vec
+                .into_iter()
+                .map(|value| Slot {
+                    generation: 0,
+                    value: Some(value),
+                })
+                .collect(),
+            free: vec![],
+            len,
+        }
+    }
+}
+
+impl<T> SlotMap<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![],
+            free: vec![],
+            len: 0,
+        }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slots: Vec::with_capacity(capacity),
+            free: vec![],
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn insert(&mut self, value: T) -> Handle {
+        self.len += 1;
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.value = Some(value);
+                Handle {
+                    index,
+                    generation: slot.generation,
+                }
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    value: Some(value),
+                });
+                Handle {
+                    index: self.slots.len() - 1,
+                    generation: 0,
+                }
+            }
+        }
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let slot = self.slots.get_mut(handle.index)?;
+        if slot.generation != handle.generation {
+            return None;
+        }
+
+        let value = slot.value.take()?;
+        slot.generation = slot.generation.wrapping_add(1);
+        self.free.push(handle.index);
+        self.len -= 1;
+        Some(value)
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.slots
+            .get(handle.index)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.slots
+            .get_mut(handle.index)
+            .filter(|slot| slot.generation == handle.generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    pub fn clear(&mut self) {
+        self.free.clear();
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            if slot.value.take().is_some() {
+                slot.generation = slot.generation.wrapping_add(1);
+            }
+            self.free.push(index);
+        }
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.value.as_ref().map(|value| {
+                (
+                    Handle {
+                        index,
+                        generation: slot.generation,
+                    },
+                    value,
+                )
+            })
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Handle, &mut T)> {
+        self.slots
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, slot)| {
+                let generation = slot.generation;
+                slot.value
+                    .as_mut()
+                    .map(|value| (Handle { index, generation }, value))
+            })
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &T> {
+        self.slots.iter().filter_map(|slot| slot.value.as_ref())
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.slots.iter_mut().filter_map(|slot| slot.value.as_mut())
+    }
+
+    ///Number of slots, occupied or not. Every slot index is below this.
+    #[inline(always)]
+    pub(crate) fn slot_len(&self) -> usize {
+        self.slots.len()
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_by_index(&self, index: usize) -> Option<&T> {
+        self.slots.get(index).and_then(|slot| slot.value.as_ref())
+    }
+
+    #[inline(always)]
+    pub(crate) fn get_mut_by_index(&mut self, index: usize) -> Option<&mut T> {
+        self.slots
+            .get_mut(index)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    ///mutable access to two different slots at once
+    pub(crate) fn get2_mut_by_index(&mut self, i: usize, j: usize) -> Option<(&mut T, &mut T)> {
+        if i == j {
+            return None;
+        }
+        let (first, second) = if i < j {
+            let (left, right) = self.slots.split_at_mut(j);
+            (&mut left[i], &mut right[0])
+        } else {
+            let (left, right) = self.slots.split_at_mut(i);
+            (&mut right[0], &mut left[j])
+        };
+        Some((first.value.as_mut()?, second.value.as_mut()?))
+    }
+
+    #[inline(always)]
+    pub(crate) fn handle_at(&self, index: usize) -> Option<Handle> {
+        self.slots
+            .get(index)
+            .filter(|slot| slot.value.is_some())
+            .map(|slot| Handle {
+                index,
+                generation: slot.generation,
+            })
+    }
+
+    ///the value of every slot, in slot order
+    #[cfg(feature = "parallel")]
+    pub(crate) fn par_slots_mut(
+        &mut self,
+    ) -> impl rayon::iter::IndexedParallelIterator<Item = Option<&mut T>>
+    where
+        T: Send,
+    {
+        use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+
+        self.slots.par_iter_mut().map(|slot| slot.value.as_mut())
+    }
+}
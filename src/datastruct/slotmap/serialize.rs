@@ -0,0 +1,23 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Slot, SlotMap};
+
+///Only the slots are stored, with their generations so the handles stay valid.
+impl<T: Serialize> Serialize for SlotMap<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.slots.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for SlotMap<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let slots: Vec<Slot<T>> = Vec::deserialize(deserializer)?;
+        let free = (0..slots.len())
+            .rev()
+            .filter(|&i| slots[i].value.is_none())
+            .collect();
+        let len = slots.iter().filter(|slot| slot.value.is_some()).count();
+
+        Ok(Self { slots, free, len })
+    }
+}
@@ -0,0 +1,78 @@
+#![cfg(test)]
+
+use crate::datastruct::slotmap::SlotMap;
+
+#[test]
+fn test_slotmap_insert_get() {
+    let mut map = SlotMap::new();
+    let a = map.insert("a");
+    let b = map.insert("b");
+
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(a), Some(&"a"));
+    assert_eq!(map.get(b), Some(&"b"));
+
+    *map.get_mut(a).unwrap() = "c";
+    assert_eq!(map.get(a), Some(&"c"));
+}
+
+#[test]
+fn test_slotmap_remove_invalidate_handle() {
+    let mut map = SlotMap::new();
+    let a = map.insert(1);
+    let b = map.insert(2);
+
+    assert_eq!(map.remove(a), Some(1));
+    assert_eq!(map.remove(a), None);
+    assert_eq!(map.get(a), None);
+    assert_eq!(map.get(b), Some(&2));
+
+    //the slot is reused, but the old handle stays invalid
+    let c = map.insert(3);
+    assert_eq!(c.index(), a.index());
+    assert_ne!(c, a);
+    assert_eq!(map.get(a), None);
+    assert_eq!(map.get(c), Some(&3));
+    assert_eq!(map.len(), 2);
+}
+
+#[test]
+fn test_slotmap_iter() {
+    let mut map: SlotMap<i32> = vec![0, 1, 2, 3].into();
+    let handles: Vec<_> = map.iter().map(|(h, _)| h).collect();
+
+    map.remove(handles[1]);
+    assert_eq!(map.values().copied().collect::<Vec<_>>(), vec![0, 2, 3]);
+
+    for (_, v) in map.iter_mut() {
+        *v *= 10;
+    }
+    assert_eq!(map.get(handles[3]), Some(&30));
+
+    map.clear();
+    assert!(map.is_empty());
+    assert!(handles.iter().all(|h| !map.contains(*h)));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_slotmap_serde() {
+    let mut map: SlotMap<String> = SlotMap::new();
+    let a = map.insert("a".to_string());
+    let b = map.insert("b".to_string());
+    let c = map.insert("c".to_string());
+    map.remove(b);
+
+    let json = serde_json::to_string(&map).unwrap();
+    let mut map: SlotMap<String> = serde_json::from_str(&json).unwrap();
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(a).map(String::as_str), Some("a"));
+    assert_eq!(map.get(c).map(String::as_str), Some("c"));
+    assert!(!map.contains(b));
+
+    //the free slot is reused with a new generation
+    let d = map.insert("d".to_string());
+    assert_eq!(d.index(), b.index());
+    assert!(!map.contains(b));
+    assert_eq!(map.len(), 3);
+}
@@ -0,0 +1,458 @@
+#[cfg(test)]
+mod test;
+
+use std::{collections::HashMap, fmt::Debug};
+
+use num::Float;
+
+use super::{
+    aabb::Aabb,
+    error::SpatialError2d,
+    points::{As2dPoint, Point},
+    shapes::Shape2d,
+    slotmap::{Handle, SlotMap},
+};
+
+type CellKey = (i64, i64);
+
+///Uniform grid of square cells of side `cell_size`, only the non-empty cells are stored (in a `HashMap`).
+///
+///Insertion, removal and moving an element are O(1), queries visit the cells they overlap.
+///It works best when the elements are evenly spread and the queries are about the size of a cell,
+///otherwise prefer a [`Quadtree`](super::quadtree::Quadtree).
+#[derive(Debug, Clone)]
+pub struct SpatialHash<F: Float + Copy + Debug, T: As2dPoint<F>> {
+    elems: SlotMap<HashElem<T>>,
+    ///slot indices of the elements of each cell
+    cells: HashMap<CellKey, Vec<usize>>,
+    cell_size: F,
+}
+
+#[derive(Debug, Clone)]
+struct HashElem<T> {
+    elem: T,
+    ///the cell the element is stored in
+    cell: CellKey,
+    ///position of the element in its cell
+    pos: usize,
+}
+
+#[allow(dead_code)]
+impl<F: Float + Copy + Debug, T: As2dPoint<F>> SpatialHash<F, T> {
+    ///Panics if `cell_size` is not > 0.
+    pub fn empty(cell_size: F) -> Self {
+        assert!(cell_size > F::zero(), "The cell size should be > 0");
+
+        Self {
+            elems: SlotMap::new(),
+            cells: HashMap::new(),
+            cell_size,
+        }
+    }
+
+    ///The element `i` of `vec` can be found using the `i`-th handle of `iter_with_handles`.
+    ///Fails if an element has invalid coordinates.
+    pub fn new(cell_size: F, vec: Vec<T>) -> Result<Self, SpatialError2d<F>> {
+        assert!(cell_size > F::zero(), "The cell size should be > 0");
+
+        let mut result = Self {
+            elems: SlotMap::with_capacity(vec.len()),
+            cells: HashMap::new(),
+            cell_size,
+        };
+        for elem in vec {
+            result.insert(elem)?;
+        }
+        Ok(result)
+    }
+
+    pub fn cell_size(&self) -> F {
+        self.cell_size
+    }
+
+    ///Changes the size of the cells and rebuilds the grid. Panics if `cell_size` is not > 0.
+    pub fn set_cell_size(&mut self, cell_size: F) -> Result<(), SpatialError2d<F>> {
+        assert!(cell_size > F::zero(), "The cell size should be > 0");
+        self.cell_size = cell_size;
+        self.rebuild()
+    }
+
+    pub fn len(&self) -> usize {
+        self.elems.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///Number of non-empty cells.
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn get(&self, handle: Handle) -> Option<&T> {
+        self.elems.get(handle).map(|h_e| &h_e.elem)
+    }
+
+    ///After moving the element, call `update_position` to move it in the grid.
+    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
+        self.elems.get_mut(handle).map(|h_e| &mut h_e.elem)
+    }
+
+    pub fn contains(&self, handle: Handle) -> bool {
+        self.elems.contains(handle)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.elems.values().map(|h_e| &h_e.elem)
+    }
+
+    ///After moving the elements, call `rebuild` to move them in the grid.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.elems.values_mut().map(|h_e| &mut h_e.elem)
+    }
+
+    pub fn iter_with_handles(&self) -> impl Iterator<Item = (Handle, &T)> {
+        self.elems.iter().map(|(handle, h_e)| (handle, &h_e.elem))
+    }
+
+    pub fn insert(&mut self, elem: T) -> Result<Handle, SpatialError2d<F>> {
+        let cell = self.cell_of(elem.as_point())?;
+
+        let handle = self.elems.insert(HashElem { elem, cell, pos: 0 });
+        self.link(handle.index(), cell);
+        Ok(handle)
+    }
+
+    ///Removes the element of `handle` and returns it, `None` if the handle is no longer valid.
+    ///Other handles stay valid.
+    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+        let h_e = self.elems.remove(handle)?;
+        self.unlink(h_e.cell, h_e.pos);
+        Some(h_e.elem)
+    }
+
+    ///Moves the element of `handle` in the grid to its current position,
+    ///to call after modifying it through `get_mut`.
+    ///On error, the element keeps its previous place in the grid.
+    pub fn update_position(&mut self, handle: Handle) -> Result<(), SpatialError2d<F>> {
+        let h_e = self
+            .elems
+            .get(handle)
+            .ok_or(SpatialError2d::InvalidHandle(handle))?;
+        let (old_cell, pos) = (h_e.cell, h_e.pos);
+        let new_cell = self.cell_of(h_e.elem.as_point())?;
+
+        if new_cell != old_cell {
+            self.unlink(old_cell, pos);
+            self.link(handle.index(), new_cell);
+        }
+        Ok(())
+    }
+
+    ///Puts every element back in the cell of its current position, in O(n).
+    ///On error, the grid is left unchanged.
+    pub fn rebuild(&mut self) -> Result<(), SpatialError2d<F>> {
+        let mut new_cells = Vec::with_capacity(self.elems.len());
+        for (handle, h_e) in self.elems.iter() {
+            new_cells.push((handle.index(), self.cell_of(h_e.elem.as_point())?));
+        }
+
+        self.cells.clear();
+        for (i, cell) in new_cells {
+            self.link(i, cell);
+        }
+        Ok(())
+    }
+
+    pub fn query_range(&self, range: Aabb<F>) -> Vec<&T> {
+        let mut result = vec![];
+        self.query_range_indices(range, &mut result);
+        result.into_iter().map(|i| self.elem_at(i)).collect()
+    }
+
+    ///Elements at most `radius` away from `center`.
+    pub fn query_circle<P: As2dPoint<F>>(&self, center: P, radius: F) -> Vec<&T> {
+        let center = center.as_point();
+        let radius_sq = radius * radius;
+
+        let range = Aabb::from_min_max(
+            (center.x - radius, center.y - radius),
+            (center.x + radius, center.y + radius),
+        );
+        self.query_range(range)
+            .into_iter()
+            .filter(|elem| elem.as_point().dist_sq(center) <= radius_sq)
+            .collect()
+    }
+
+    ///Elements contained in `shape`. Shapes have no bounds, so every non-empty cell is tested.
+    pub fn query_shape<Sh: Shape2d<F>>(&self, shape: &Sh) -> Vec<&T> {
+        self.cells
+            .iter()
+            .filter(|(key, _)| shape.intersects_aabb(self.cell_aabb(**key)))
+            .flat_map(|(_, cell)| cell.iter().map(|&i| self.elem_at(i)))
+            .filter(|elem| shape.contains_point(elem.as_point()))
+            .collect()
+    }
+
+    ///Elements in the cell of `point` and in the 8 cells around it,
+    ///so every element less than `cell_size` away from `point` (and a few more).
+    pub fn query_neighbours<P: As2dPoint<F>>(&self, point: P) -> Vec<&T> {
+        let Some((cx, cy)) = self.clamped_cell_of(point.as_point()) else {
+            return vec![];
+        };
+
+        (cx.saturating_sub(1)..=cx.saturating_add(1))
+            .flat_map(|x| (cy.saturating_sub(1)..=cy.saturating_add(1)).map(move |y| (x, y)))
+            .filter_map(|key| self.cells.get(&key))
+            .flat_map(|cell| cell.iter().map(|&i| self.elem_at(i)))
+            .collect()
+    }
+
+    ///Closest element to `point` with its distance, `None` if the grid is empty.
+    pub fn nearest<P: As2dPoint<F>>(&self, point: P) -> Option<(&T, F)> {
+        self.k_nearest(point, 1).into_iter().next()
+    }
+
+    ///The `k` closest elements to `point` with their distances, sorted by distance.
+    ///The cells are visited by rings around the cell of `point`.
+    pub fn k_nearest<P: As2dPoint<F>>(&self, point: P, k: usize) -> Vec<(&T, F)> {
+        let point = point.as_point();
+        let Some((cx, cy)) = self.clamped_cell_of(point) else {
+            return vec![];
+        };
+        if k == 0 {
+            return vec![];
+        }
+        //past the keys, the rings around the clamped cell would not be sorted by distance
+        let far = self.cell_of(point).is_err();
+
+        let mut candidates: Vec<(F, usize)> = vec![];
+        let push_cell = |candidates: &mut Vec<(F, usize)>, cell: &Vec<usize>| {
+            candidates.extend(
+                cell.iter()
+                    .map(|&i| (self.elem_at(i).as_point().dist_sq(point), i)),
+            );
+        };
+
+        let mut ring = 0i64;
+        loop {
+            //a ring has 8 * ring cells, past the number of cells it is cheaper to look at all of them
+            if far || (8 * ring) as usize >= self.cells.len() {
+                for (&(x, y), cell) in &self.cells {
+                    if x.abs_diff(cx).max(y.abs_diff(cy)) >= ring as u64 {
+                        push_cell(&mut candidates, cell);
+                    }
+                }
+                break;
+            }
+
+            for key in ring_cells((cx, cy), ring) {
+                if let Some(cell) = self.cells.get(&key) {
+                    push_cell(&mut candidates, cell);
+                }
+            }
+
+            //the cells of the next rings are at least `ring * cell_size` away from the point
+            if candidates.len() >= k {
+                candidates
+                    .sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+                candidates.truncate(k);
+                let reach = F::from(ring).unwrap() * self.cell_size;
+                if candidates[k - 1].0 <= reach * reach {
+                    break;
+                }
+            }
+            ring += 1;
+        }
+
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        candidates
+            .into_iter()
+            .take(k)
+            .map(|(dist_sq, i)| (self.elem_at(i), dist_sq.sqrt()))
+            .collect()
+    }
+
+    pub fn map_query_range(&mut self, range: Aabb<F>, map: impl Fn(&mut T)) {
+        let mut indices = vec![];
+        self.query_range_indices(range, &mut indices);
+        for i in indices {
+            map(self.elem_at_mut(i));
+        }
+    }
+
+    ///For each element, `map` is called with every other element in `range_mapping(elem)`,
+    ///the element of lower slot first, like `Quadtree::map_with_elem_in_range`.
+    ///The grid is not updated, call `rebuild` if the elements moved.
+    pub fn map_with_elem_in_range(
+        &mut self,
+        range_mapping: impl Fn(&T) -> Aabb<F>,
+        map: impl Fn(&mut T, &mut T),
+    ) {
+        let mut range = vec![];
+        for i in 0..self.elems.slot_len() {
+            let Some(h_e) = self.elems.get_by_index(i) else {
+                continue;
+            };
+            range.clear();
+            self.query_range_indices(range_mapping(&h_e.elem), &mut range);
+
+            for &i_p in &range {
+                if i_p != i {
+                    let (a, b) = self.elems_at_mut(i.min(i_p), i.max(i_p));
+                    map(a, b);
+                }
+            }
+        }
+    }
+
+    ///slot indices of the elements in `range`
+    fn query_range_indices(&self, range: Aabb<F>, result: &mut Vec<usize>) {
+        let (Some(min), Some(max)) = (
+            self.clamped_cell_of(range.min()),
+            self.clamped_cell_of(range.max()),
+        ) else {
+            return;
+        };
+        let mut push_cell = |cell: &Vec<usize>| {
+            result.extend(
+                cell.iter()
+                    .copied()
+                    .filter(|&i| range.contain_pt(self.elem_at(i).as_point())),
+            );
+        };
+
+        //a range much bigger than the occupied cells would visit a lot of empty cells,
+        //`None` if there are more cells than an i64 can count
+        let side = |min: i64, max: i64| max.checked_sub(min)?.checked_add(1);
+        let range_cells = side(min.0, max.0)
+            .zip(side(min.1, max.1))
+            .and_then(|(w, h)| w.checked_mul(h));
+        if range_cells.is_none_or(|nb| nb as usize > self.cells.len()) {
+            for (&(x, y), cell) in &self.cells {
+                if (min.0..=max.0).contains(&x) && (min.1..=max.1).contains(&y) {
+                    push_cell(cell);
+                }
+            }
+        } else {
+            for x in min.0..=max.0 {
+                for y in min.1..=max.1 {
+                    if let Some(cell) = self.cells.get(&(x, y)) {
+                        push_cell(cell);
+                    }
+                }
+            }
+        }
+    }
+
+    fn cell_of(&self, point: Point<F>) -> Result<CellKey, SpatialError2d<F>> {
+        let invalid = SpatialError2d::InvalidCoord((point.x, point.y));
+        if !point.as_valid_coord() {
+            return Err(invalid);
+        }
+        let x = (point.x / self.cell_size).floor().to_i64().ok_or(invalid)?;
+        let y = (point.y / self.cell_size).floor().to_i64().ok_or(invalid)?;
+        Ok((x, y))
+    }
+
+    ///cell of a query point, clamped to the keys instead of failing, `None` if it is NaN
+    fn clamped_cell_of(&self, point: Point<F>) -> Option<CellKey> {
+        let key = |coord: F| {
+            let k = (coord / self.cell_size).floor();
+            if k.is_nan() {
+                None
+            } else if k > F::zero() {
+                Some(k.to_i64().unwrap_or(i64::MAX))
+            } else {
+                Some(k.to_i64().unwrap_or(i64::MIN))
+            }
+        };
+        Some((key(point.x)?, key(point.y)?))
+    }
+
+    fn cell_aabb(&self, (x, y): CellKey) -> Aabb<F> {
+        let half = self.cell_size / (F::one() + F::one());
+        let to_f = |k: i64| F::from(k).unwrap_or(F::nan());
+        Aabb::new(
+            (
+                to_f(x) * self.cell_size + half,
+                to_f(y) * self.cell_size + half,
+            ),
+            half,
+        )
+    }
+
+    ///adds the slot `i` at the end of `cell`
+    fn link(&mut self, i: usize, cell: CellKey) {
+        let indices = self.cells.entry(cell).or_default();
+        indices.push(i);
+        let pos = indices.len() - 1;
+        if let Some(h_e) = self.elems.get_mut_by_index(i) {
+            h_e.cell = cell;
+            h_e.pos = pos;
+        }
+    }
+
+    ///removes the element at `pos` in `cell`, the last element of the cell takes its place
+    fn unlink(&mut self, cell: CellKey, pos: usize) {
+        let Some(indices) = self.cells.get_mut(&cell) else {
+            return;
+        };
+        indices.swap_remove(pos);
+        match indices.get(pos) {
+            Some(&moved) => {
+                if let Some(h_e) = self.elems.get_mut_by_index(moved) {
+                    h_e.pos = pos;
+                }
+            }
+            None if indices.is_empty() => {
+                self.cells.remove(&cell);
+            }
+            None => (),
+        }
+    }
+
+    ///element of a slot referenced by the grid
+    #[inline(always)]
+    fn elem_at(&self, i: usize) -> &T {
+        &self
+            .elems
+            .get_by_index(i)
+            .expect("something went wrong in SpatialHash: the grid refers to an empty slot")
+            .elem
+    }
+
+    #[inline(always)]
+    fn elem_at_mut(&mut self, i: usize) -> &mut T {
+        &mut self
+            .elems
+            .get_mut_by_index(i)
+            .expect("something went wrong in SpatialHash: the grid refers to an empty slot")
+            .elem
+    }
+
+    #[inline(always)]
+    fn elems_at_mut(&mut self, i: usize, j: usize) -> (&mut T, &mut T) {
+        let (a, b) = self
+            .elems
+            .get2_mut_by_index(i, j)
+            .expect("something went wrong in SpatialHash: the grid refers to an empty slot");
+        (&mut a.elem, &mut b.elem)
+    }
+}
+
+///the `8 * ring` cells at Tchebychev distance `ring` of `center`, `center` itself for ring 0
+fn ring_cells((cx, cy): CellKey, ring: i64) -> Vec<CellKey> {
+    if ring == 0 {
+        return vec![(cx, cy)];
+    }
+    //there is no cell past the keys
+    let cell = |dx: i64, dy: i64| Some((cx.checked_add(dx)?, cy.checked_add(dy)?));
+    (-ring..=ring)
+        .flat_map(|d| [cell(d, -ring), cell(d, ring)])
+        .chain((1 - ring..ring).flat_map(|d| [cell(-ring, d), cell(ring, d)]))
+        .flatten()
+        .collect()
+}
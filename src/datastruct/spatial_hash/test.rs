@@ -0,0 +1,227 @@
+#![cfg(test)]
+
+use crate::datastruct::{
+    aabb::Aabb, points::As2dPoint, quadtree::Quadtree, shapes::Circle, spatial_hash::SpatialHash,
+    test_util::sorted_coords,
+};
+
+#[derive(Debug, Clone)]
+struct Particle {
+    x: f64,
+    y: f64,
+    hits: usize,
+}
+
+impl As2dPoint<f64> for Particle {
+    fn x(&self) -> f64 {
+        self.x
+    }
+    fn y(&self) -> f64 {
+        self.y
+    }
+}
+
+///points on a spiral, some of them with negative coordinates
+fn spiral_points(nb: usize) -> Vec<(f64, f64)> {
+    (0..nb)
+        .map(|i| {
+            let t = i as f64 * 0.37;
+            (t.cos() * t, t.sin() * t)
+        })
+        .collect()
+}
+
+#[test]
+fn test_spatial_hash_queries() {
+    let points = spiral_points(500);
+    let hash = SpatialHash::new(7.5, points.clone()).unwrap();
+    assert_eq!(hash.len(), 500);
+    assert!(hash.cell_count() > 1);
+
+    for range in [
+        Aabb::new((0., 0.), 30.),
+        Aabb::new_rect((-100., 50.), 60., 20.),
+        Aabb::new((15., -15.), 2.),
+        //much bigger than the occupied cells
+        Aabb::new((0., 0.), 1e6),
+    ] {
+        let expected = points.iter().filter(|p| range.contain_pt(p.as_point()));
+        assert_eq!(
+            sorted_coords(hash.query_range(range)),
+            sorted_coords(expected)
+        );
+    }
+
+    let (center, radius) = ((20., -40.), 45.);
+    let expected = points
+        .iter()
+        .filter(|p| p.as_point().dist((center).as_point()) <= radius);
+    assert_eq!(
+        sorted_coords(hash.query_circle(center, radius)),
+        sorted_coords(expected)
+    );
+    assert_eq!(
+        sorted_coords(hash.query_shape(&Circle::new(center, radius))),
+        sorted_coords(hash.query_circle(center, radius))
+    );
+
+    //the 3x3 cells around the point cover at least a cell size around it
+    let neighbours = hash.query_neighbours((10., 10.));
+    let close = hash.query_circle((10., 10.), 7.5);
+    assert!(neighbours.len() >= close.len());
+    for p in close {
+        assert!(
+            neighbours
+                .iter()
+                .any(|n| n.as_point().dist(p.as_point()) == 0.)
+        );
+    }
+    assert!(hash.query_neighbours((1e9, 1e9)).is_empty());
+}
+
+#[test]
+fn test_spatial_hash_wide_ranges() {
+    let points = spiral_points(200);
+    let mut hash = SpatialHash::new(0.5, points.clone()).unwrap();
+    let all = sorted_coords(points.iter());
+
+    //corners far past the cell keys, clamped instead of failing
+    assert_eq!(
+        sorted_coords(hash.query_range(Aabb::new((0., 0.), 1e300))),
+        all
+    );
+    assert_eq!(sorted_coords(hash.query_circle((0., 0.), 1e300)), all);
+    assert!(hash.query_range(Aabb::new((1e300, 0.), 1e299)).is_empty());
+    assert!(hash.query_neighbours((1e300, -1e300)).is_empty());
+
+    //more cells than an i64 can count, along both axes
+    let range = Aabb::from_min_max((-4.5e18, -4.5e18), (4.5e18, 4.5e18));
+    assert_eq!(sorted_coords(hash.query_range(range)), all);
+
+    let closest = points
+        .iter()
+        .map(|p| p.as_point().dist((1e300, 0.).as_point()))
+        .fold(f64::INFINITY, f64::min);
+    assert_eq!(hash.nearest((1e300, 0.)).unwrap().1, closest);
+    assert_eq!(hash.k_nearest((-1e300, 1e300), 500).len(), 200);
+
+    //the error stays for the elements
+    assert!(hash.insert((1e300, 0.)).is_err());
+    assert!(hash.query_range(Aabb::new((f64::NAN, 0.), 1.)).is_empty());
+}
+
+#[test]
+fn test_spatial_hash_k_nearest() {
+    let mut points = spiral_points(400);
+    //far from the others, to go through a lot of empty rings
+    points.push((5000., -3000.));
+    let hash = SpatialHash::new(4., points.clone()).unwrap();
+
+    for target in [(13., 27.), (0., 0.), (-500., 800.), (4999., -2999.)] {
+        let mut expected: Vec<_> = points
+            .iter()
+            .map(|p| p.as_point().dist(target.as_point()))
+            .collect();
+        expected.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let result = hash.k_nearest(target, 20);
+        assert_eq!(result.len(), 20);
+        for ((elem, d), e) in result.iter().zip(&expected) {
+            assert!((d - e).abs() < 1e-9);
+            assert!((elem.as_point().dist(target.as_point()) - d).abs() < 1e-9);
+        }
+        assert_eq!(hash.nearest(target).unwrap().1, expected[0]);
+    }
+    assert_eq!(hash.k_nearest((0., 0.), 1000).len(), 401);
+    assert!(hash.k_nearest((0., 0.), 0).is_empty());
+    assert!(
+        SpatialHash::<f64, (f64, f64)>::empty(1.)
+            .nearest((0., 0.))
+            .is_none()
+    );
+}
+
+#[test]
+fn test_spatial_hash_insert_remove_update() {
+    let mut hash = SpatialHash::empty(10.);
+    let points = spiral_points(300);
+    let handles: Vec<_> = points.iter().map(|p| hash.insert(*p).unwrap()).collect();
+    assert!(hash.insert((f64::NAN, 0.)).is_err());
+    assert!(hash.insert((1e300, 0.)).is_err());
+
+    for handle in &handles[..200] {
+        assert!(hash.remove(*handle).is_some());
+        assert!(hash.remove(*handle).is_none());
+        assert!(!hash.contains(*handle));
+    }
+    assert_eq!(hash.len(), 100);
+    assert_eq!(hash.query_range(Aabb::new((0., 0.), 1000.)).len(), 100);
+
+    for handle in &handles[200..] {
+        let p = hash.get_mut(*handle).unwrap();
+        p.0 = -p.0;
+        hash.update_position(*handle).unwrap();
+    }
+    let range = Aabb::new((-20., 10.), 40.);
+    let expected: Vec<_> = points[200..]
+        .iter()
+        .map(|&(x, y)| (-x, y))
+        .filter(|p| range.contain_pt(p.as_point()))
+        .collect();
+    assert_eq!(
+        sorted_coords(hash.query_range(range)),
+        sorted_coords(expected.iter())
+    );
+
+    hash.get_mut(handles[250]).unwrap().0 = f64::INFINITY;
+    assert!(hash.update_position(handles[250]).is_err());
+    assert!(hash.update_position(handles[0]).is_err());
+    assert!(hash.rebuild().is_err());
+
+    hash.get_mut(handles[250]).unwrap().0 = 0.;
+    for p in hash.iter_mut() {
+        p.1 += 100.;
+    }
+    hash.set_cell_size(3.).unwrap();
+    assert_eq!(hash.query_range(Aabb::new((0., -100.), 50.)).len(), 0);
+    assert_eq!(hash.query_range(Aabb::new((0., 100.), 1000.)).len(), 100);
+
+    for handle in &handles[200..] {
+        hash.remove(*handle);
+    }
+    assert!(hash.is_empty());
+    assert_eq!(hash.cell_count(), 0);
+}
+
+#[test]
+fn test_spatial_hash_map_with_elem_in_range() {
+    let particles: Vec<_> = spiral_points(300)
+        .into_iter()
+        .map(|(x, y)| Particle { x, y, hits: 0 })
+        .collect();
+    let range_mapping = |p: &Particle| Aabb::new((p.x, p.y), 3.);
+    let count = |a: &mut Particle, b: &mut Particle| {
+        a.hits += 1;
+        b.hits += 1;
+    };
+
+    let mut hash = SpatialHash::new(3., particles.clone()).unwrap();
+    hash.map_with_elem_in_range(range_mapping, count);
+    let mut qtree: Quadtree<f64, Particle, 4> = Quadtree::new(Aabb::new((0., 0.), 200.), particles);
+    qtree.map_with_elem_in_range(range_mapping, count);
+
+    let hits = |iter: Vec<&Particle>| {
+        let mut hits: Vec<_> = iter.into_iter().map(|p| (p.x, p.y, p.hits)).collect();
+        hits.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        hits
+    };
+    assert_eq!(hits(hash.iter().collect()), hits(qtree.iter().collect()));
+    assert!(hash.iter().any(|p| p.hits > 0));
+
+    hash.map_query_range(Aabb::new((0., 0.), 5.), |p| p.hits = 0);
+    assert!(
+        hash.query_range(Aabb::new((0., 0.), 5.))
+            .iter()
+            .all(|p| p.hits == 0)
+    );
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+mod test;
+
+use std::fmt::Debug;
+
+use num::Float;
+
+use super::{
+    aabb::Aabb,
+    error::SpatialError2d,
+    points::As2dPoint,
+    quadtree::{ArenaQuadtree, Quadtree, QueryMode, Summary},
+    slotmap::Handle,
+    spatial_hash::SpatialHash,
+};
+
+///Dynamic container of points, to write code generic over the spatial structure.
+///
+///The range queries of the trait always filter the elements exactly, whatever the `QueryMode` of
+///a `Quadtree`, while its inherent methods follow the mode and may return elements out of range.
+pub trait SpatialIndex<F: Float + Copy + Debug, T: As2dPoint<F>> {
+    fn insert(&mut self, elem: T) -> Result<Handle, SpatialError2d<F>>;
+
+    ///Removes the element of `handle` and returns it, `None` if the handle is no longer valid.
+    fn remove(&mut self, handle: Handle) -> Option<T>;
+
+    ///Elements inside `range`, bounds included, whatever the `QueryMode` of a `Quadtree`.
+    fn query_range(&self, range: Aabb<F>) -> Vec<&T>;
+
+    ///Closest element to `point` with its distance, `None` if there is no element.
+    fn nearest<P: As2dPoint<F>>(&self, point: P) -> Option<(&T, F)>;
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize, S: Summary<T>> SpatialIndex<F, T>
+    for Quadtree<F, T, N, S>
+{
+    fn insert(&mut self, elem: T) -> Result<Handle, SpatialError2d<F>> {
+        Quadtree::insert(self, elem)
+    }
+
+    fn remove(&mut self, handle: Handle) -> Option<T> {
+        Quadtree::remove(self, handle)
+    }
+
+    fn query_range(&self, range: Aabb<F>) -> Vec<&T> {
+        let mut result = Quadtree::query_range(self, range);
+        //the broad phase also returns elements of the leaves crossing the range
+        if self.query_mode() == QueryMode::BroadPhase {
+            result.retain(|elem| range.contain_pt(elem.as_point()));
+        }
+        result
+    }
+
+    fn nearest<P: As2dPoint<F>>(&self, point: P) -> Option<(&T, F)> {
+        Quadtree::nearest(self, point)
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a,
+    {
+        Quadtree::iter(self)
+    }
+
+    fn len(&self) -> usize {
+        Quadtree::len(self)
+    }
+}
+
+impl<F: Float + Copy + Debug, T: As2dPoint<F>, const N: usize> SpatialIndex<F, T>
+    for ArenaQuadtree<F, T, N>
+{
+    fn insert(&mut self, elem: T) -> Result<Handle, SpatialError2d<F>> {
+        ArenaQuadtree::insert(self, elem)
+    }
+
+    fn remove(&mut self, handle: Handle) -> Option<T> {
+        ArenaQuadtree::remove(self, handle)
+    }
+
+    fn query_range(&self, range: Aabb<F>) -> Vec<&T> {
+        ArenaQuadtree::query_range(self, range)
+    }
+
+    fn nearest<P: As2dPoint<F>>(&self, point: P) -> Option<(&T, F)> {
+        ArenaQuadtree::nearest(self, point)
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a,
+    {
+        ArenaQuadtree::iter(self)
+    }
+
+    fn len(&self) -> usize {
+        ArenaQuadtree::len(self)
+    }
+}
+
+impl<F: Float + Copy + Debug, T: As2dPoint<F>> SpatialIndex<F, T> for SpatialHash<F, T> {
+    fn insert(&mut self, elem: T) -> Result<Handle, SpatialError2d<F>> {
+        SpatialHash::insert(self, elem)
+    }
+
+    fn remove(&mut self, handle: Handle) -> Option<T> {
+        SpatialHash::remove(self, handle)
+    }
+
+    fn query_range(&self, range: Aabb<F>) -> Vec<&T> {
+        SpatialHash::query_range(self, range)
+    }
+
+    fn nearest<P: As2dPoint<F>>(&self, point: P) -> Option<(&T, F)> {
+        SpatialHash::nearest(self, point)
+    }
+
+    fn iter<'a>(&'a self) -> impl Iterator<Item = &'a T>
+    where
+        T: 'a,
+    {
+        SpatialHash::iter(self)
+    }
+
+    fn len(&self) -> usize {
+        SpatialHash::len(self)
+    }
+}
@@ -0,0 +1,128 @@
+#![cfg(test)]
+
+use crate::datastruct::{
+    aabb::Aabb,
+    points::As2dPoint,
+    quadtree::{ArenaQuadtree, Quadtree, QueryMode},
+    slotmap::Handle,
+    spatial_hash::SpatialHash,
+    spatial_index::SpatialIndex,
+    test_util::{Rng, SEEDS, sorted_coords},
+};
+
+const HALF_SIZE: f64 = 100.;
+
+///a point of the boundary, often on a grid so that some points coincide or sit on node edges
+fn random_point(rng: &mut Rng) -> (f64, f64) {
+    if rng.below(4) == 0 {
+        let snap = |x: f64| (x / 12.5).round() * 12.5;
+        (
+            snap(rng.range(-HALF_SIZE, HALF_SIZE)),
+            snap(rng.range(-HALF_SIZE, HALF_SIZE)),
+        )
+    } else {
+        (
+            rng.range(-HALF_SIZE, HALF_SIZE),
+            rng.range(-HALF_SIZE, HALF_SIZE),
+        )
+    }
+}
+
+///Runs random insertions, removals and queries on `index`, checking every result against a `Vec`.
+fn check_conformance<I: SpatialIndex<f64, (f64, f64)>>(mut index: I, seed: u64) {
+    let mut rng = Rng::new(seed);
+    let mut reference: Vec<(Handle, (f64, f64))> = vec![];
+
+    for step in 0..3000 {
+        match rng.below(10) {
+            0..=4 => {
+                let p = random_point(&mut rng);
+                let handle = index.insert(p).unwrap();
+                assert!(reference.iter().all(|(h, _)| *h != handle));
+                reference.push((handle, p));
+            }
+            5..=6 if !reference.is_empty() => {
+                let (handle, p) = reference.swap_remove(rng.below(reference.len()));
+                assert_eq!(index.remove(handle), Some(p));
+                assert_eq!(index.remove(handle), None);
+            }
+            7 => {
+                let center = random_point(&mut rng);
+                let range = Aabb::new_rect(center, rng.range(0.1, 60.), rng.range(0.1, 60.));
+                let expected = reference
+                    .iter()
+                    .map(|(_, p)| p)
+                    .filter(|p| range.contain_pt(p.as_point()));
+                assert_eq!(
+                    sorted_coords(index.query_range(range)),
+                    sorted_coords(expected),
+                    "step {}",
+                    step
+                );
+            }
+            8 => {
+                let target = (rng.range(-150., 150.), rng.range(-150., 150.));
+                let expected = reference
+                    .iter()
+                    .map(|(_, p)| p.as_point().dist(target.as_point()))
+                    .min_by(|a, b| a.partial_cmp(b).unwrap());
+                match (index.nearest(target), expected) {
+                    (Some((elem, d)), Some(e)) => {
+                        assert!((d - e).abs() < 1e-9, "step {}", step);
+                        assert!((elem.as_point().dist(target.as_point()) - d).abs() < 1e-9);
+                    }
+                    (None, None) => (),
+                    (found, _) => {
+                        panic!("step {}: found {:?}, expected {:?}", step, found, expected)
+                    }
+                }
+            }
+            _ => {
+                assert!(index.insert((f64::NAN, 0.)).is_err());
+                assert_eq!(index.len(), reference.len());
+                assert_eq!(index.is_empty(), reference.is_empty());
+                assert_eq!(
+                    sorted_coords(index.iter()),
+                    sorted_coords(reference.iter().map(|(_, p)| p))
+                );
+            }
+        }
+    }
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_quadtree_conformance() {
+    for seed in SEEDS {
+        let qtree: Quadtree<f64, (f64, f64), 4> = Quadtree::empty(Aabb::new((0., 0.), HALF_SIZE));
+        check_conformance(qtree, seed);
+    }
+    let shallow: Quadtree<f64, (f64, f64), 2> =
+        Quadtree::empty(Aabb::new((0., 0.), HALF_SIZE)).with_max_depth(3);
+    check_conformance(shallow, 7);
+    //the trait filters the results exactly
+    let broad: Quadtree<f64, (f64, f64), 4> =
+        Quadtree::empty(Aabb::new((0., 0.), HALF_SIZE)).with_query_mode(QueryMode::BroadPhase);
+    check_conformance(broad, 3);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_arena_quadtree_conformance() {
+    for seed in SEEDS {
+        let arena: ArenaQuadtree<f64, (f64, f64), 4> =
+            ArenaQuadtree::empty(Aabb::new((0., 0.), HALF_SIZE));
+        check_conformance(arena, seed);
+    }
+    let shallow: ArenaQuadtree<f64, (f64, f64), 2> =
+        ArenaQuadtree::empty(Aabb::new((0., 0.), HALF_SIZE)).with_max_depth(3);
+    check_conformance(shallow, 7);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn test_spatial_hash_conformance() {
+    for (seed, cell_size) in [(1, 10.), (0xdead_beef, 12.5), (42, 0.7), (7, 500.)] {
+        check_conformance(SpatialHash::empty(cell_size), seed);
+    }
+}
@@ -0,0 +1,70 @@
+#![cfg(test)]
+//!helpers shared by the tests of the spatial structures
+
+use num::Float;
+
+use super::points::{As2dPoint, As3dPoint};
+
+///seeds of the randomized tests
+pub(crate) const SEEDS: [u64; 3] = [1, 0xdead_beef, 42];
+
+///xorshift64*, random but the same on every run, enough for the tests without pulling a crate
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    ///`seed` must not be 0
+    pub(crate) fn new(seed: u64) -> Self {
+        debug_assert!(seed != 0, "xorshift is stuck at 0");
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    ///in `[0, 1)`
+    pub(crate) fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    ///in `[0, 1)`
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u32 << 24) as f32
+    }
+
+    ///in `[min, max)`
+    pub(crate) fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+
+    ///in `[min, max)`
+    pub(crate) fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    ///in `0..n`
+    pub(crate) fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+///coordinates of the elements, sorted to compare the results of queries
+pub(crate) fn sorted_coords<'a, F: Float + Copy, P: As2dPoint<F> + 'a>(
+    elems: impl IntoIterator<Item = &'a P>,
+) -> Vec<(F, F)> {
+    let mut coords: Vec<_> = elems.into_iter().map(|p| (p.x(), p.y())).collect();
+    coords.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    coords
+}
+
+///3d version of `sorted_coords`
+pub(crate) fn sorted_coords3<'a, F: Float + Copy, P: As3dPoint<F> + 'a>(
+    elems: impl IntoIterator<Item = &'a P>,
+) -> Vec<(F, F, F)> {
+    let mut coords: Vec<_> = elems.into_iter().map(|p| (p.x(), p.y(), p.z())).collect();
+    coords.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    coords
+}
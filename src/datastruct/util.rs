@@ -0,0 +1,31 @@
+//!helpers shared by the spatial structures
+
+use num::Float;
+
+///the box from `min` to `max` grown to contain all the `points`, then by a margin of 1 on every axis,
+///NaN coordinates are ignored
+pub(crate) fn grown_bounds<F: Float, const D: usize>(
+    mut min: [F; D],
+    mut max: [F; D],
+    points: impl Iterator<Item = [F; D]>,
+) -> ([F; D], [F; D]) {
+    for point in points {
+        for ((lo, hi), v) in min.iter_mut().zip(max.iter_mut()).zip(point) {
+            *lo = lo.min(v);
+            *hi = hi.max(v);
+        }
+    }
+    (min.map(|v| v - F::one()), max.map(|v| v + F::one()))
+}
+
+///moves the elements satisfying `pred` first, returns their number
+pub(crate) fn partition<E>(slice: &mut [E], pred: impl Fn(&E) -> bool) -> usize {
+    let mut nb = 0;
+    for k in 0..slice.len() {
+        if pred(&slice[k]) {
+            slice.swap(nb, k);
+            nb += 1;
+        }
+    }
+    nb
+}